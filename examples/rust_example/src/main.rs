@@ -1,14 +1,13 @@
-use wowsunpacker::{
-    game_directory::{GameDirectory, GameServer},
-    game_unpack::Unpacker,
-};
+use wowsunpacker::{logger::setup_default_logger, unpacker::Recipe};
 
+// cargo run -- recipe.toml
 fn main() {
-    let mut games = GameDirectory::new();
-    games.locate();
-    let game_dirtory = games.get_game_directory(GameServer::WW).unwrap();
-    let unpacker = Unpacker::new_auto(game_dirtory).unwrap();
-    unpacker
-        .extract_exact("content/GameParams.data", "output")
-        .unwrap();
+    setup_default_logger();
+
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "recipe.toml".to_string());
+    let recipe = Recipe::from_file(&path).unwrap();
+    let summary = recipe.run().unwrap();
+    summary.info().write_to_file("summary.json", &recipe.dest).unwrap();
 }
@@ -0,0 +1,731 @@
+use std::{io::Write, path::Path, process::ExitCode};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use wowsunpacker::{
+    game::{GameDirectory, GameLanguages, GameServer},
+    logger::{setup_default_logger, setup_logger},
+    types::UnpackResult,
+    unpacker::{
+        normalize_path, ErrorMode, ExtractSummary, FileRecord, FileSource, GameParams,
+        GameUnpacker, IndexEntry, IndexFormat, LangDiff, LangMerger, LangReport, LangUnpacker,
+        ParamsDecoder, ParamsDiff, ParamsFormat, ParamsSplitter, Precedence, Recipe, SplitLayout,
+        TextSearch, UnpackEvent, GAME_PARAMS_PATH,
+    },
+};
+
+// 0 on success and 2 for invalid arguments, which clap handles
+const EXIT_FAILURE: u8 = 1;
+// nothing matched, e.g. no game directory or no file for a query
+const EXIT_NOT_FOUND: u8 = 3;
+// files which can't be read back from their pkg, found by verify or extract
+const EXIT_CORRUPTED: u8 = 4;
+
+#[derive(Parser)]
+#[command(
+    name = "wowsunpack",
+    version,
+    about = "Unpack the files of World of Warships"
+)]
+#[command(
+    after_help = "Exit codes: 0 success, 1 error, 2 invalid arguments, 3 nothing found, 4 corrupted files"
+)]
+struct Cli {
+    /// The game directory, located from the registry when omitted
+    #[arg(short, long, global = true)]
+    game_path: Option<String>,
+
+    /// The server to locate when no game path is given: ww, cn or pt
+    #[arg(short, long, global = true, default_value = "ww")]
+    server: GameServer,
+
+    /// The build under game_path/bin, the latest one when omitted
+    #[arg(short, long, global = true)]
+    build: Option<u32>,
+
+    /// The directory output files are written to
+    #[arg(short, long, global = true, default_value = "output")]
+    dest: String,
+
+    /// How listings are printed
+    #[arg(short, long, global = true, value_enum, default_value_t = Output::Text)]
+    output: Output,
+
+    /// Only print the requested output and errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Show a progress bar while extracting
+    #[arg(long, global = true, conflicts_with = "quiet")]
+    progress: bool,
+
+    /// Overlay the loose files of bin/<build>/res_mods on the packed files
+    #[arg(long, global = true)]
+    res_mods: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the game directories found in the registry
+    Locate,
+    /// Print the builds with an idx folder, the latest first
+    Builds,
+    /// List the files and directories of a packed directory
+    Ls {
+        /// e.g. gui/4k, the root when omitted
+        #[arg(default_value = "")]
+        path: String,
+
+        /// List everything below the directory
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Search the packed files, * is a wildcard
+    Search { query: String },
+    /// Extract packed files or directories into dest
+    Extract {
+        /// Exact paths like gui/4k/, or patterns like gui/*.png where * stays within one directory
+        #[arg(required = true)]
+        patterns: Vec<String>,
+
+        /// Keep extracting after a file fails and report every failure
+        #[arg(short, long)]
+        keep_going: bool,
+    },
+    /// Write a packed file to stdout
+    Cat { path: String },
+    /// Decode, merge or search the strings of global.mo
+    Lang(LangArgs),
+    /// Decode GameParams.data
+    Params(ParamsArgs),
+    /// Read back the matching files and check them against the idx records
+    Verify {
+        #[arg(default_value = "*")]
+        pattern: String,
+    },
+    /// List the records hidden by a record of the same path in another idx file or res_mods
+    Duplicates {
+        /// The pkg names which win in this order, otherwise the first idx by name wins
+        #[arg(long, value_delimiter = ',')]
+        pkg_order: Vec<String>,
+    },
+    /// Compare the strings or the GameParams of two builds
+    Diff(DiffArgs),
+    /// Write the records of the packed files as JSON lines, CSV or SQLite
+    Export {
+        /// The output file, the format is guessed from the extension
+        file: String,
+
+        /// jsonl, csv or sqlite
+        #[arg(short, long)]
+        format: Option<IndexFormat>,
+
+        /// Only export the files matching a search query
+        #[arg(long)]
+        query: Option<String>,
+    },
+    /// Run a TOML or json extraction recipe, --game-path and --build override the recipe
+    Recipe { file: String },
+}
+
+#[derive(Args)]
+struct LangArgs {
+    /// e.g. en,ja, every installed language when omitted
+    #[arg(short, long, value_delimiter = ',')]
+    lang: Vec<GameLanguages>,
+
+    /// Write one table with a value per language for each key
+    #[arg(long)]
+    merged: bool,
+
+    /// Write the translation coverage compared to en
+    #[arg(long, conflicts_with = "merged")]
+    report: bool,
+
+    /// Only keep keys with these prefixes, e.g. IDS_PASB,IDS_PJSB
+    #[arg(long, value_delimiter = ',')]
+    prefix: Vec<String>,
+
+    /// Print the strings containing the query instead of writing files
+    #[arg(long, conflicts_with_all = ["merged", "report"])]
+    search: Option<String>,
+
+    /// Treat the search query as a regex
+    #[arg(long, requires = "search")]
+    regex: bool,
+}
+
+#[derive(Args)]
+struct ParamsArgs {
+    /// Decode this GameParams.data instead of the one in the game
+    #[arg(short, long)]
+    input: Option<String>,
+
+    /// json, msgpack or cbor
+    #[arg(short, long, default_value = "json")]
+    format: ParamsFormat,
+
+    /// Skip pretty printing of json
+    #[arg(long)]
+    compact: bool,
+
+    /// Keep the Python types as tagged json
+    #[arg(long, conflicts_with_all = ["format", "split"])]
+    lossless: bool,
+
+    /// Write one json per type or per entity instead of one file
+    #[arg(long, value_enum)]
+    split: Option<Split>,
+
+    /// Only split these typeinfo types, e.g. Ship,Modernization
+    #[arg(long = "type", value_delimiter = ',', requires = "split")]
+    types: Vec<String>,
+
+    /// Skip these typeinfo types
+    #[arg(long = "exclude-type", value_delimiter = ',', requires = "split")]
+    exclude_types: Vec<String>,
+
+    /// Only split these nations, e.g. USA,Japan
+    #[arg(long = "nation", value_delimiter = ',', requires = "split")]
+    nations: Vec<String>,
+
+    /// Skip these nations
+    #[arg(long = "exclude-nation", value_delimiter = ',', requires = "split")]
+    exclude_nations: Vec<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Split {
+    Type,
+    Entity,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Compare GameParams instead of the strings
+    #[arg(long)]
+    params: bool,
+
+    /// The language to compare
+    #[arg(short, long, default_value = "en", conflicts_with = "params")]
+    lang: GameLanguages,
+
+    /// The game directory of the old build, the same game when omitted
+    #[arg(long)]
+    old_path: Option<String>,
+
+    /// The old build, the one before the new build when omitted
+    #[arg(long)]
+    old_build: Option<u32>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if cli.quiet {
+        setup_logger("off", "off");
+    } else if cli.progress {
+        // info lines would tear the progress bar
+        setup_logger("warn", "warn");
+    } else {
+        setup_default_logger();
+    }
+
+    match run(&cli) {
+        Ok(code) => ExitCode::from(code),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+fn run(cli: &Cli) -> UnpackResult<u8> {
+    match &cli.command {
+        Command::Locate => locate(cli),
+        Command::Builds => {
+            let builds = GameUnpacker::builds(&game_path(cli)?)?;
+            print_list(cli, &builds)?;
+            Ok(if builds.is_empty() { EXIT_NOT_FOUND } else { 0 })
+        }
+        Command::Ls { path, recursive } => ls(cli, path, *recursive),
+        Command::Search { query } => {
+            let mut files = open_with_tree(cli)?.search(query, false)?;
+            files.sort();
+            print_list(cli, &files)?;
+            Ok(if files.is_empty() { EXIT_NOT_FOUND } else { 0 })
+        }
+        Command::Extract {
+            patterns,
+            keep_going,
+        } => extract(cli, patterns, *keep_going),
+        Command::Cat { path } => {
+            let data = open_with_tree(cli)?.read(path)?;
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&data)?;
+            stdout.flush()?;
+            Ok(0)
+        }
+        Command::Lang(args) => lang(cli, args),
+        Command::Params(args) => params(cli, args),
+        Command::Verify { pattern } => {
+            let report = open_with_tree(cli)?.verify(pattern)?;
+            match cli.output {
+                Output::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                Output::Text => {
+                    for failure in &report.failures {
+                        println!("{}: {}", failure.path, failure.error);
+                    }
+                    println!(
+                        "Checked {} files, {} failed",
+                        report.checked,
+                        report.failures.len()
+                    );
+                }
+            }
+            Ok(if report.is_ok() { 0 } else { EXIT_CORRUPTED })
+        }
+        Command::Duplicates { pkg_order } => {
+            let mut unpacker = open(&game_path(cli)?, cli.build)?.with_res_mods(cli.res_mods);
+            if !pkg_order.is_empty() {
+                unpacker = unpacker.with_precedence(Precedence::PkgOrder(pkg_order.clone()));
+            }
+            unpacker.build_directory_tree()?;
+            let shadowed = unpacker.shadowed();
+            match cli.output {
+                Output::Json => println!("{}", serde_json::to_string_pretty(shadowed)?),
+                Output::Text => {
+                    for record in shadowed {
+                        println!(
+                            "{} ({} hidden by {})",
+                            record.record.path,
+                            record.record.pkg_name,
+                            source_name(&record.winner)
+                        );
+                    }
+                }
+            }
+            Ok(0)
+        }
+        Command::Diff(args) => diff(cli, args),
+        Command::Export {
+            file,
+            format,
+            query,
+        } => {
+            let format = format
+                .or_else(|| IndexFormat::from_path(file))
+                .ok_or("Unknown index format, pass --format")?;
+            let unpacker = open_with_tree(cli)?;
+            let count = match query {
+                Some(query) => unpacker.export_search(query, file, format)?,
+                None => unpacker.export_index(file, format)?,
+            };
+            if !cli.quiet {
+                eprintln!("Exported {} records to {}", count, file);
+            }
+            Ok(if count == 0 { EXIT_NOT_FOUND } else { 0 })
+        }
+        Command::Recipe { file } => {
+            let mut recipe = Recipe::from_file(file)?;
+            if cli.game_path.is_some() {
+                recipe.game_path = cli.game_path.clone();
+            }
+            if cli.build.is_some() {
+                recipe.build = cli.build;
+            }
+            let summary = recipe.run()?;
+            match cli.output {
+                Output::Json => println!("{}", serde_json::to_string_pretty(&summary)?),
+                Output::Text => {
+                    println!(
+                        "Extracted {} files ({} bytes), {} excluded",
+                        summary.extracted.iter().sum::<usize>(),
+                        summary.bytes,
+                        summary.excluded
+                    );
+                    println!("Decoded {} languages", summary.languages.len());
+                    if let Some(params) = &summary.params {
+                        println!("GameParams written to {}", params);
+                    }
+                }
+            }
+            Ok(0)
+        }
+    }
+}
+
+fn game_path(cli: &Cli) -> UnpackResult<String> {
+    match &cli.game_path {
+        Some(path) => Ok(path.clone()),
+        None => Ok(GameDirectory::new()
+            .locate()
+            .get_game_directory(cli.server)
+            .ok_or(format!(
+                "Failed to find the {:?} game directory, pass --game-path",
+                cli.server
+            ))?
+            .to_string()),
+    }
+}
+
+fn open(path: &str, build: Option<u32>) -> UnpackResult<GameUnpacker> {
+    match build {
+        Some(build) => GameUnpacker::with_build(path, build),
+        None => GameUnpacker::auto(path),
+    }
+}
+
+fn open_with_tree(cli: &Cli) -> UnpackResult<GameUnpacker> {
+    let mut unpacker = open(&game_path(cli)?, cli.build)?
+        .with_case_insensitive_lookup(true)
+        .with_res_mods(cli.res_mods);
+    if cli.progress {
+        unpacker = unpacker.with_observer(progress_bar()?);
+    }
+    unpacker.build_directory_tree()?;
+    Ok(unpacker)
+}
+
+/// Render the events of an unpacker as a progress bar of the uncompressed bytes
+fn progress_bar() -> UnpackResult<impl Fn(&UnpackEvent) + Send + Sync> {
+    let bar = ProgressBar::new(0);
+    bar.set_style(ProgressStyle::with_template(
+        "{bar:40} {bytes}/{total_bytes} {wide_msg}",
+    )?);
+    Ok(move |event: &UnpackEvent| match event {
+        UnpackEvent::IndexParsed { idx_name, files } => {
+            bar.set_message(format!("Parsed {} ({} files)", idx_name, files))
+        }
+        UnpackEvent::ExtractStarted { files, bytes } => {
+            bar.inc_length(*bytes);
+            bar.set_message(format!("Extracting {} files", files));
+        }
+        UnpackEvent::RecordStarted { path, .. } => bar.set_message(path.to_string()),
+        UnpackEvent::RecordFinished { bytes, .. } => bar.inc(*bytes),
+        UnpackEvent::Error { path, error } => bar.println(format!("{}: {}", path, error)),
+    })
+}
+
+fn print_list<T: Serialize + ToString>(cli: &Cli, items: &[T]) -> UnpackResult<()> {
+    match cli.output {
+        Output::Json => println!("{}", serde_json::to_string_pretty(items)?),
+        Output::Text => {
+            for item in items {
+                println!("{}", item.to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn locate(cli: &Cli) -> UnpackResult<u8> {
+    let mut directory = GameDirectory::new();
+    directory.locate();
+    let found: Vec<(String, String)> = GameServer::values()
+        .into_iter()
+        .filter_map(|server| {
+            directory
+                .get_game_directory(server)
+                .map(|path| (format!("{:?}", server), path.clone()))
+        })
+        .collect();
+
+    match cli.output {
+        Output::Json => {
+            let found: serde_json::Map<String, serde_json::Value> = found
+                .iter()
+                .map(|(server, path)| (server.clone(), path.clone().into()))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&found)?);
+        }
+        Output::Text => {
+            for (server, path) in &found {
+                println!("{}: {}", server, path);
+            }
+        }
+    }
+    Ok(if found.is_empty() { EXIT_NOT_FOUND } else { 0 })
+}
+
+/// The pkg name of a record, or res_mods for a loose file
+fn source_name(record: &FileRecord) -> &str {
+    match record.source {
+        FileSource::Pkg => &record.pkg_name,
+        FileSource::ResMods => record.source.name(),
+    }
+}
+
+/// A search query for a pattern where * is the only wildcard and stays within one path segment
+fn wildcard_query(pattern: &str) -> String {
+    let pieces: Vec<String> = normalize_path(pattern)
+        .split('*')
+        .map(regex::escape)
+        .collect();
+    // search() turns every * into .*, so the segment wildcard is written without one
+    format!("(^|/){}(/|$)", pieces.join("[^/]{0,}"))
+}
+
+fn ls(cli: &Cli, path: &str, recursive: bool) -> UnpackResult<u8> {
+    let unpacker = open_with_tree(cli)?;
+    let stat = match unpacker.stat(path) {
+        Ok(stat) => stat,
+        Err(err) => {
+            // the error suggests the closest paths
+            if !cli.quiet {
+                eprintln!("{}", err);
+            }
+            return Ok(EXIT_NOT_FOUND);
+        }
+    };
+
+    let entries: Vec<IndexEntry> = match (recursive, stat.is_dir) {
+        (true, _) => unpacker.walk(path)?.collect(),
+        (false, true) => unpacker.list(path)?,
+        (false, false) => vec![stat],
+    };
+    match cli.output {
+        Output::Json => println!("{}", serde_json::to_string_pretty(&entries)?),
+        Output::Text => {
+            for entry in &entries {
+                match &entry.record {
+                    Some(record) if record.source == FileSource::ResMods => {
+                        println!("{:>12} {} (res_mods)", record.uncompressed_size, entry.path)
+                    }
+                    Some(record) => println!("{:>12} {}", record.uncompressed_size, entry.path),
+                    None => println!("{:>12} {}/", "-", entry.path),
+                }
+            }
+        }
+    }
+    Ok(0)
+}
+
+fn extract(cli: &Cli, patterns: &[String], keep_going: bool) -> UnpackResult<u8> {
+    let unpacker = open_with_tree(cli)?;
+    let mode = match keep_going {
+        true => ErrorMode::Continue,
+        false => ErrorMode::FailFast,
+    };
+
+    let mut code = 0;
+    let mut total = ExtractSummary::default();
+    for pattern in patterns {
+        // the remaining patterns are not attempted either
+        if mode == ErrorMode::FailFast && !total.is_ok() {
+            break;
+        }
+
+        let summary = if pattern.contains('*') {
+            unpacker.extract_matching(&wildcard_query(pattern), &cli.dest, mode)?
+        } else {
+            match unpacker.stat(pattern) {
+                Ok(_) => unpacker.extract_node(pattern, &cli.dest, mode)?,
+                Err(err) => {
+                    if !cli.quiet {
+                        eprintln!("{}", err);
+                    }
+                    code = EXIT_NOT_FOUND;
+                    continue;
+                }
+            }
+        };
+        if summary.written.is_empty() && summary.skipped.is_empty() && summary.is_ok() {
+            if !cli.quiet {
+                eprintln!("No files match {}", pattern);
+            }
+            code = EXIT_NOT_FOUND;
+            continue;
+        }
+        total.written.extend(summary.written);
+        total.bytes += summary.bytes;
+        total.skipped.extend(summary.skipped);
+        total.failures.extend(summary.failures);
+    }
+
+    match cli.output {
+        Output::Json => println!("{}", serde_json::to_string_pretty(&total)?),
+        Output::Text if !cli.quiet || !total.is_ok() => {
+            for failure in &total.failures {
+                eprintln!(
+                    "{} ({} at {}): {}",
+                    failure.path, failure.pkg_name, failure.offset, failure.error
+                );
+            }
+            println!(
+                "Extracted {} files ({} bytes), {} skipped, {} failed",
+                total.written.len(),
+                total.bytes,
+                total.skipped.len(),
+                total.failures.len()
+            );
+        }
+        Output::Text => {}
+    }
+    Ok(if total.is_ok() { code } else { EXIT_CORRUPTED })
+}
+
+fn lang(cli: &Cli, args: &LangArgs) -> UnpackResult<u8> {
+    let unpacker = open(&game_path(cli)?, cli.build)?;
+    let prefixes: Vec<&str> = args.prefix.iter().map(|p| p.as_str()).collect();
+    if args.merged || args.report || args.search.is_some() {
+        // one table with a value per language for each key
+        let mut merger = LangMerger::new();
+        if !args.lang.is_empty() {
+            merger.languages(&args.lang);
+        }
+        merger.prefixes(&prefixes).merge(&unpacker)?;
+
+        if let Some(query) = &args.search {
+            let search = if args.regex {
+                TextSearch::regex(query)?
+            } else {
+                TextSearch::substring(query)?
+            };
+            let matches = search.search_merged(&merger);
+            match cli.output {
+                Output::Json => println!("{}", serde_json::to_string_pretty(&matches)?),
+                Output::Text => {
+                    for text_match in &matches {
+                        println!(
+                            "{} [{}]: {}",
+                            text_match.key, text_match.language, text_match.value
+                        );
+                    }
+                }
+            }
+            return Ok(if matches.is_empty() {
+                EXIT_NOT_FOUND
+            } else {
+                0
+            });
+        }
+
+        if args.report {
+            let report = LangReport::from_merged(&merger, &GameLanguages::EN)?;
+            report.info().write_to_file("lang_report.json", &cli.dest)?;
+            if cli.output == Output::Json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        } else {
+            merger.write_to_file("lang.json", &cli.dest)?;
+        }
+        return Ok(0);
+    }
+
+    let languages = if args.lang.is_empty() {
+        // not every client ships all languages
+        unpacker.installed_languages()?.languages
+    } else {
+        args.lang.clone()
+    };
+    std::fs::create_dir_all(&cli.dest)?;
+    for lang in languages.iter() {
+        LangUnpacker::new(unpacker.get_lang_path(lang))?
+            .decode()?
+            .write_to_file(&lang.to_filename(), &cli.dest)?;
+    }
+    Ok(0)
+}
+
+fn as_strs(values: &[String]) -> Vec<&str> {
+    values.iter().map(|value| value.as_str()).collect()
+}
+
+fn params(cli: &Cli, args: &ParamsArgs) -> UnpackResult<u8> {
+    let data = match &args.input {
+        Some(input) => std::fs::read(input)?,
+        None => open_with_tree(cli)?.read(GAME_PARAMS_PATH)?,
+    };
+
+    if args.lossless {
+        let value = ParamsDecoder::decode_lossless(&data)?;
+        let file_path = Path::new(&cli.dest).join("GameParams.lossless.json");
+        ParamsDecoder::write_lossless_to_file(
+            &value,
+            file_path.to_str().ok_or("Invalid destination")?,
+            args.compact,
+        )?;
+        return Ok(0);
+    }
+
+    let params = ParamsDecoder::decode(&data)?;
+    if let Some(split) = args.split {
+        let layout = match split {
+            Split::Type => SplitLayout::PerType,
+            Split::Entity => SplitLayout::PerEntity,
+        };
+        let summary = ParamsSplitter::new(layout)
+            .compact(args.compact)
+            .include_types(&as_strs(&args.types))
+            .exclude_types(&as_strs(&args.exclude_types))
+            .include_nations(&as_strs(&args.nations))
+            .exclude_nations(&as_strs(&args.exclude_nations))
+            .split(&GameParams::new(params)?, &cli.dest)?;
+        if cli.output == Output::Json {
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        }
+        return Ok(0);
+    }
+
+    let file_path = Path::new(&cli.dest).join(format!("GameParams.{}", args.format.extension()));
+    args.format.write(
+        &params,
+        file_path.to_str().ok_or("Invalid destination")?,
+        args.compact,
+    )?;
+    Ok(0)
+}
+
+fn diff(cli: &Cli, args: &DiffArgs) -> UnpackResult<u8> {
+    let new_path = game_path(cli)?;
+    let old_path = args.old_path.clone().unwrap_or_else(|| new_path.clone());
+
+    let new_builds = GameUnpacker::builds(&new_path)?;
+    let new_build = cli
+        .build
+        .or_else(|| new_builds.first().copied())
+        .ok_or("Failed to find the new build")?;
+    let old_build = match args.old_build {
+        Some(build) => build,
+        // the previous build of the same install, or the latest of another install
+        None if old_path == new_path => *new_builds
+            .iter()
+            .find(|build| **build < new_build)
+            .ok_or("Failed to find an older build to compare with, pass --old-build")?,
+        None => *GameUnpacker::builds(&old_path)?
+            .first()
+            .ok_or("Failed to find the old build")?,
+    };
+
+    let mut old = GameUnpacker::with_build(&old_path, old_build)?;
+    let mut new = GameUnpacker::with_build(&new_path, new_build)?;
+    std::fs::create_dir_all(&cli.dest)?;
+    if args.params {
+        old.build_directory_tree()?;
+        new.build_directory_tree()?;
+        let diff = ParamsDiff::between(&old, &new)?;
+        diff.write_to_file("params_diff.json", &cli.dest)?;
+        diff.write_report("params_diff.txt", &cli.dest)?;
+        if cli.output == Output::Text && !cli.quiet {
+            print!("{}", diff.to_text());
+        }
+    } else {
+        let diff = LangDiff::between(&old, &new, &args.lang)?;
+        diff.info()
+            .write_to_file(&format!("diff_{}", args.lang.to_filename()), &cli.dest)?;
+        if cli.output == Output::Text && !cli.quiet {
+            print!("{}", diff.to_text());
+        }
+    }
+    Ok(0)
+}
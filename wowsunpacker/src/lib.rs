@@ -24,9 +24,36 @@ pub mod logger {
 }
 
 pub mod unpacker {
-    pub use crate::unpack::game_unpack::GameUnpacker;
+    pub use crate::unpack::game_event::{EventCallback, UnpackEvent};
+    pub use crate::unpack::game_export::IndexFormat;
+    pub use crate::unpack::game_recipe::{
+        ExtractRule, LangRecipe, ParamsRecipe, Recipe, RecipeSummary,
+    };
+    pub use crate::unpack::game_unpack::{
+        normalize_path, ErrorMode, ExtractFailure, ExtractSummary, FileRecord, FileSource,
+        GameUnpacker, IndexEntry, InstalledLanguages, Precedence, ResolvedFile, ShadowedRecord,
+        TreeWalk, VerifyFailure, VerifyReport, GAME_PARAMS_PATH,
+    };
+    pub use crate::unpack::lang_diff::{ChangedString, LangDiff};
+    pub use crate::unpack::lang_merge::{LangMerger, MergedStrings};
+    pub use crate::unpack::lang_report::{LangReport, LanguageCoverage, PlaceholderMismatch};
+    pub use crate::unpack::lang_search::{MatchRank, TextMatch, TextSearch};
     pub use crate::unpack::lang_unpack::LangUnpacker;
+    pub use crate::unpack::params_decode::ParamsDecoder;
+    pub use crate::unpack::params_diff::{ParamsDiff, ValueChange};
+    pub use crate::unpack::params_encode::ParamsEncoder;
+    pub use crate::unpack::params_format::ParamsFormat;
+    pub use crate::unpack::params_localize::{
+        ids_keys, LocalizedEntity, LocalizedNames, LocalizedParams, ParamsLocalizer,
+    };
+    pub use crate::unpack::params_model::{Entity, GameParams, TypeInfo, TypeInfoFilter};
+    pub use crate::unpack::params_resolve::{
+        DanglingReference, ParamsResolver, Reference, ReferenceKind, ShipView,
+    };
+    pub use crate::unpack::params_split::{ParamsSplitter, SplitLayout, SplitSummary};
     pub use crate::unpack::params_unpack::ParamsUnpacker;
+    pub use crate::unpack::params_value::{PyObject, PyValue};
+    pub use crate::utils::pickle::ObjectKind;
 }
 
 pub mod game {
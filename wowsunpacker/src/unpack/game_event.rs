@@ -0,0 +1,32 @@
+// Events of GameUnpacker, so callers can show progress instead of reading the log
+
+/// Called with every event, see GameUnpacker::with_observer()
+pub type EventCallback = Box<dyn Fn(&UnpackEvent) + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnpackEvent<'a> {
+    /// An idx file was added to the directory tree
+    IndexParsed {
+        idx_name: &'a str,
+        files: usize,
+    },
+    /// An extraction is about to write these totals, sizes are uncompressed
+    ExtractStarted {
+        files: usize,
+        bytes: u64,
+    },
+    RecordStarted {
+        path: &'a str,
+        size: u64,
+    },
+    /// A file was written to the destination
+    RecordFinished {
+        path: &'a str,
+        bytes: u64,
+    },
+    /// A record could not be extracted
+    Error {
+        path: &'a str,
+        error: &'a str,
+    },
+}
@@ -0,0 +1,263 @@
+// Dump the records of the directory tree for analysis, e.g. to browse the assets of a build
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use log::info;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::types::UnpackResult;
+use crate::unpack::game_unpack::FileRecord;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// One json object per line
+    JsonLines,
+    Csv,
+    /// A files table with indexes on path, pkg_name and id
+    Sqlite,
+}
+
+#[derive(Serialize)]
+struct IndexRow<'a> {
+    path: &'a str,
+    pkg_name: &'a str,
+    id: u64,
+    offset: i64,
+    size: i32,
+    uncompressed_size: i64,
+    build: Option<u32>,
+    /// pkg or res_mods
+    source: &'static str,
+}
+
+impl<'a> IndexRow<'a> {
+    fn new(record: &'a FileRecord, build: Option<u32>) -> Self {
+        Self {
+            path: &record.path,
+            pkg_name: &record.pkg_name,
+            id: record.id,
+            offset: record.offset,
+            size: record.size,
+            uncompressed_size: record.uncompressed_size,
+            build,
+            source: record.source.name(),
+        }
+    }
+}
+
+impl IndexFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            IndexFormat::JsonLines => "jsonl",
+            IndexFormat::Csv => "csv",
+            IndexFormat::Sqlite => "sqlite",
+        }
+    }
+
+    /// Guess the format from the file extension, e.g. index.csv
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        extension.parse().ok()
+    }
+
+    /**
+     * Write the records in this format, an existing file is replaced
+     * @param records The records to write
+     * @param build The build of the records, written to every row
+     * @param file_path The output file, parent directories are created
+     * @return The number of rows
+     */
+    pub fn write<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a FileRecord>,
+        build: Option<u32>,
+        file_path: &str,
+    ) -> UnpackResult<usize> {
+        if let Some(parent) = Path::new(file_path).parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        let rows = records
+            .into_iter()
+            .map(|record| IndexRow::new(record, build));
+        let count = match self {
+            IndexFormat::JsonLines => {
+                let mut writer = BufWriter::new(File::create(file_path)?);
+                let mut count = 0;
+                for row in rows {
+                    serde_json::to_writer(&mut writer, &row)?;
+                    writer.write_all(b"\n")?;
+                    count += 1;
+                }
+                writer.flush()?;
+                count
+            }
+            IndexFormat::Csv => {
+                let mut writer = csv::Writer::from_path(file_path)?;
+                let mut count = 0;
+                for row in rows {
+                    writer.serialize(row)?;
+                    count += 1;
+                }
+                writer.flush()?;
+                count
+            }
+            IndexFormat::Sqlite => write_sqlite(rows, file_path)?,
+        };
+        info!("Exported {} records to {} as {:?}", count, file_path, self);
+        Ok(count)
+    }
+}
+
+fn write_sqlite<'a>(
+    rows: impl Iterator<Item = IndexRow<'a>>,
+    file_path: &str,
+) -> UnpackResult<usize> {
+    if Path::new(file_path).exists() {
+        std::fs::remove_file(file_path)?;
+    }
+
+    let mut connection = Connection::open(file_path)?;
+    // sqlite has no unsigned integers, id keeps the bits of the u64
+    connection.execute_batch(
+        "CREATE TABLE files (
+            path TEXT NOT NULL,
+            pkg_name TEXT NOT NULL,
+            id INTEGER NOT NULL,
+            offset INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            uncompressed_size INTEGER NOT NULL,
+            build INTEGER,
+            source TEXT NOT NULL
+        );",
+    )?;
+
+    let transaction = connection.transaction()?;
+    let mut count = 0;
+    {
+        let mut insert = transaction.prepare(
+            "INSERT INTO files (path, pkg_name, id, offset, size, uncompressed_size, build, source)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for row in rows {
+            insert.execute(params![
+                row.path,
+                row.pkg_name,
+                row.id as i64,
+                row.offset,
+                row.size,
+                row.uncompressed_size,
+                row.build,
+                row.source,
+            ])?;
+            count += 1;
+        }
+    }
+    // faster to build the indexes once all rows are in
+    transaction.execute_batch(
+        "CREATE INDEX files_path ON files (path);
+         CREATE INDEX files_pkg_name ON files (pkg_name);
+         CREATE INDEX files_id ON files (id);",
+    )?;
+    transaction.commit()?;
+    Ok(count)
+}
+
+impl FromStr for IndexFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jsonl" | "ndjson" | "json-lines" => Ok(IndexFormat::JsonLines),
+            "csv" => Ok(IndexFormat::Csv),
+            "sqlite" | "sqlite3" | "db" => Ok(IndexFormat::Sqlite),
+            _ => Err(format!("Unknown index format {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IndexFormat;
+    use crate::unpack::test_utils::{temp_dir, unpacker_with, TEST_BUILD};
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_export_index() {
+        let unpacker = unpacker_with(&[(
+            "gui_0001.pkg",
+            &[
+                ("gui/4k/logo.png", &[1, 2, 3]),
+                ("gui/text, \"quoted\".txt", &[4]),
+            ],
+        )]);
+
+        let dest = temp_dir("export");
+        let dest = dest.display();
+        let jsonl = format!("{}/index.jsonl", dest);
+        assert_eq!(
+            unpacker
+                .export_index(&jsonl, IndexFormat::JsonLines)
+                .unwrap(),
+            2
+        );
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&jsonl)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0]["path"], "gui/4k/logo.png");
+        assert_eq!(lines[0]["uncompressed_size"], 3);
+        assert_eq!(lines[0]["build"], TEST_BUILD);
+        assert_eq!(lines[0]["source"], "pkg");
+
+        let csv = format!("{}/index.csv", dest);
+        unpacker.export_index(&csv, IndexFormat::Csv).unwrap();
+        let mut reader = csv::Reader::from_path(&csv).unwrap();
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec![
+                "path",
+                "pkg_name",
+                "id",
+                "offset",
+                "size",
+                "uncompressed_size",
+                "build",
+                "source"
+            ]
+        );
+        let rows: Vec<csv::StringRecord> = reader.records().map(|row| row.unwrap()).collect();
+        assert_eq!(&rows[1][0], "gui/text, \"quoted\".txt");
+        assert_eq!(&rows[1][1], "gui_0001.pkg");
+
+        // written twice to check the old database is replaced
+        let sqlite = format!("{}/index.sqlite", dest);
+        for _ in 0..2 {
+            let count = unpacker
+                .export_search("*.png", &sqlite, IndexFormat::Sqlite)
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+        let connection = Connection::open(&sqlite).unwrap();
+        let (path, size): (String, i64) = connection
+            .query_row(
+                "SELECT path, uncompressed_size FROM files WHERE pkg_name = 'gui_0001.pkg'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((path.as_str(), size), ("gui/4k/logo.png", 3));
+
+        assert_eq!(IndexFormat::from_path(&csv), Some(IndexFormat::Csv));
+        assert!("xml".parse::<IndexFormat>().is_err());
+    }
+}
@@ -0,0 +1,394 @@
+// Run the same extraction every patch from a TOML or json recipe, e.g.
+//
+// server = "ww"
+// dest = "output"
+//
+// [[extract]]
+// include = ["gui/4k/*", "gui/dogTags/medium/*"]
+// exclude = ["*.dds"]
+// dest = "gui"
+// strip = "gui/"
+//
+// [lang]
+// languages = ["en", "ja"]
+// dest = "texts"
+//
+// [params]
+// format = "msgpack"
+
+use std::{fs::File, io::Write, path::Path};
+
+use log::info;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::types::UnpackResult;
+use crate::unpack::game_unpack::{normalize_path, GameUnpacker, GAME_PARAMS_PATH};
+use crate::unpack::lang_merge::LangMerger;
+use crate::unpack::lang_unpack::LangUnpacker;
+use crate::unpack::params_decode::ParamsDecoder;
+use crate::unpack::params_format::ParamsFormat;
+use crate::utils::game::{GameDirectory, GameLanguages, GameServer};
+
+fn default_dest() -> String {
+    "output".to_string()
+}
+
+fn default_format() -> ParamsFormat {
+    ParamsFormat::Json
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recipe {
+    /// The game directory, the server is located from the registry when this is missing
+    pub game_path: Option<String>,
+    pub server: Option<GameServer>,
+    /// The build under game_path/bin, the latest one when missing
+    pub build: Option<u32>,
+    /// Every output path of the recipe is relative to this directory
+    #[serde(default = "default_dest")]
+    pub dest: String,
+    #[serde(default)]
+    pub extract: Vec<ExtractRule>,
+    pub lang: Option<LangRecipe>,
+    pub params: Option<ParamsRecipe>,
+}
+
+/// Files to extract, the patterns are globs on the whole path where * also matches /
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtractRule {
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// The directory under the recipe dest
+    #[serde(default)]
+    pub dest: String,
+    /// A directory removed from the packed paths, e.g. gui/ to write gui/4k/ into dest/4k/
+    pub strip: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LangRecipe {
+    /// Every installed language when empty
+    #[serde(default)]
+    pub languages: Vec<GameLanguages>,
+    #[serde(default)]
+    pub dest: String,
+    /// Write one lang.json with a value per language instead of one file per language
+    #[serde(default)]
+    pub merged: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParamsRecipe {
+    #[serde(default = "default_format")]
+    pub format: ParamsFormat,
+    #[serde(default)]
+    pub compact: bool,
+    /// The output file under the recipe dest, GameParams.<extension> when missing
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RecipeSummary {
+    pub build: Option<u32>,
+    /// The number of files written by each extract rule
+    pub extracted: Vec<usize>,
+    /// Files matched by an include pattern but dropped by an exclude pattern
+    pub excluded: usize,
+    pub bytes: u64,
+    pub languages: Vec<GameLanguages>,
+    /// The GameParams output file
+    pub params: Option<String>,
+}
+
+impl RecipeSummary {
+    pub fn info(&self) -> &Self {
+        info!(
+            "Recipe of build {:?}: {} files ({} bytes), {} excluded, {} languages, params {}",
+            self.build,
+            self.extracted.iter().sum::<usize>(),
+            self.bytes,
+            self.excluded,
+            self.languages.len(),
+            self.params.as_deref().unwrap_or("skipped")
+        );
+        self
+    }
+
+    pub fn write_to_file(&self, file_name: &str, dest: &str) -> UnpackResult<()> {
+        let file_path = Path::new(&dest).join(file_name);
+        let mut file = File::create(file_path)?;
+        let json = serde_json::to_string_pretty(&self)?;
+        file.write_all(json.as_bytes())?;
+        info!("Recipe summary written to {}/{}", dest, file_name);
+        Ok(())
+    }
+}
+
+impl Recipe {
+    /// Load a recipe, .toml files are read as TOML and everything else as json
+    pub fn from_file(path: &str) -> UnpackResult<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let extension = Path::new(path).extension().and_then(|ext| ext.to_str());
+        if matches!(extension, Some("toml")) {
+            Self::from_toml(&content)
+        } else {
+            Self::from_json(&content)
+        }
+    }
+
+    pub fn from_toml(content: &str) -> UnpackResult<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn from_json(content: &str) -> UnpackResult<Self> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    /// Open the game and the build selected by the recipe
+    pub fn unpacker(&self) -> UnpackResult<GameUnpacker> {
+        let game_path = match &self.game_path {
+            Some(game_path) => game_path.clone(),
+            None => {
+                let server = self.server.unwrap_or(GameServer::WW);
+                GameDirectory::new()
+                    .locate()
+                    .get_game_directory(server)
+                    .ok_or(format!("Failed to find the {:?} game directory", server))?
+                    .to_string()
+            }
+        };
+
+        match self.build {
+            Some(build) => GameUnpacker::with_build(&game_path, build),
+            None => GameUnpacker::auto(&game_path),
+        }
+    }
+
+    /// Open the game and run the whole recipe
+    pub fn run(&self) -> UnpackResult<RecipeSummary> {
+        let mut unpacker = self.unpacker()?;
+        self.run_with(&mut unpacker)
+    }
+
+    /**
+     * Run the recipe on an opened game, the directory tree is built when files are extracted
+     * @param unpacker The game, its build is not checked against the recipe
+     * @return What was written
+     */
+    pub fn run_with(&self, unpacker: &mut GameUnpacker) -> UnpackResult<RecipeSummary> {
+        let mut summary = RecipeSummary {
+            build: unpacker.build(),
+            ..Default::default()
+        };
+        if !self.extract.is_empty() || self.params.is_some() {
+            unpacker.build_directory_tree()?;
+        }
+
+        for rule in &self.extract {
+            let count = self.extract_rule(unpacker, rule, &mut summary)?;
+            summary.extracted.push(count);
+        }
+
+        if let Some(lang) = &self.lang {
+            let dest = Path::new(&self.dest).join(&lang.dest);
+            let dest = dest.to_str().ok_or("Invalid lang destination")?;
+            std::fs::create_dir_all(dest)?;
+            let languages = if lang.languages.is_empty() {
+                unpacker.installed_languages()?.languages
+            } else {
+                lang.languages.clone()
+            };
+
+            if lang.merged {
+                LangMerger::new()
+                    .languages(&languages)
+                    .merge(unpacker)?
+                    .write_to_file("lang.json", dest)?;
+            } else {
+                for language in &languages {
+                    LangUnpacker::new(unpacker.get_lang_path(language))?
+                        .decode()?
+                        .write_to_file(&language.to_filename(), dest)?;
+                }
+            }
+            summary.languages = languages;
+        }
+
+        if let Some(params) = &self.params {
+            let file_name = match &params.file {
+                Some(file) => file.clone(),
+                None => format!("GameParams.{}", params.format.extension()),
+            };
+            let file_path = Path::new(&self.dest).join(file_name);
+            let file_path = file_path.to_str().ok_or("Invalid params destination")?;
+            let decoded = ParamsDecoder::decode(&unpacker.read(GAME_PARAMS_PATH)?)?;
+            params.format.write(&decoded, file_path, params.compact)?;
+            summary.params = Some(file_path.to_string());
+        }
+
+        Ok(summary)
+    }
+
+    fn extract_rule(
+        &self,
+        unpacker: &GameUnpacker,
+        rule: &ExtractRule,
+        summary: &mut RecipeSummary,
+    ) -> UnpackResult<usize> {
+        let includes = glob_regexes(&rule.include)?;
+        let excludes = glob_regexes(&rule.exclude)?;
+        let dest = Path::new(&self.dest).join(&rule.dest);
+
+        let mut files = Vec::new();
+        for entry in unpacker.walk("")? {
+            let record = match entry.record {
+                Some(record) => record,
+                None => continue,
+            };
+            if !includes
+                .iter()
+                .any(|include| include.is_match(&record.path))
+            {
+                continue;
+            }
+            if excludes
+                .iter()
+                .any(|exclude| exclude.is_match(&record.path))
+            {
+                summary.excluded += 1;
+                continue;
+            }
+            files.push(record);
+        }
+        // the tree has no order, keep the output stable between runs
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let files: Vec<_> = files
+            .iter()
+            .map(|record| {
+                let relative = match &rule.strip {
+                    Some(prefix) => strip_dir(&record.path, prefix),
+                    None => &record.path,
+                };
+                (record, dest.join(relative))
+            })
+            .collect();
+        summary.bytes += unpacker.extract_to(&files)?;
+
+        info!("Extracted {} files to {}", files.len(), dest.display());
+        Ok(files.len())
+    }
+}
+
+/// Anchored case insensitive regexes of glob patterns, * is the only wildcard
+fn glob_regexes(patterns: &[String]) -> UnpackResult<Vec<Regex>> {
+    let mut regexes = Vec::new();
+    for pattern in patterns {
+        let pieces: Vec<String> = pattern.split('*').map(regex::escape).collect();
+        regexes.push(Regex::new(&format!("(?i)^{}$", pieces.join(".*")))?);
+    }
+    Ok(regexes)
+}
+
+/// Remove a leading directory from a packed path, the case is ignored like in the patterns
+fn strip_dir<'a>(path: &'a str, dir: &str) -> &'a str {
+    let dir = normalize_path(dir);
+    match (path.get(..dir.len()), path.get(dir.len()..)) {
+        (Some(head), Some(rest)) if !dir.is_empty() && head.eq_ignore_ascii_case(&dir) => {
+            rest.strip_prefix('/').unwrap_or(path)
+        }
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_dir, Recipe};
+    use crate::unpack::{
+        game_event::UnpackEvent,
+        test_utils::{temp_dir, unpacker_with, TEST_BUILD},
+    };
+    use crate::utils::game::GameLanguages;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_run_recipe() {
+        let unpacker = unpacker_with(&[(
+            "gui_0001.pkg",
+            &[
+                ("gui/4k/logo.png", &[1, 2, 3]),
+                ("gui/4k/logo.dds", &[4, 5]),
+                ("gui/dogTags/medium/tag.png", &[6]),
+                ("gui/fonts/font.ttf", &[7]),
+            ],
+        )]);
+        let dest = temp_dir("recipe");
+        let recipe = Recipe::from_toml(&format!(
+            r#"
+            dest = '{}'
+
+            [[extract]]
+            include = ["gui/4k/*", "gui/dogtags/*", "4k/*"]
+            exclude = ["*.dds"]
+            dest = "gui"
+            strip = "GUI/"
+            "#,
+            dest.display()
+        ))
+        .unwrap();
+
+        let started = Arc::new(Mutex::new(Vec::new()));
+        let events = started.clone();
+        let mut unpacker = unpacker.with_observer(move |event| {
+            if let UnpackEvent::ExtractStarted { files, bytes } = event {
+                events.lock().unwrap().push((*files, *bytes));
+            }
+        });
+        let summary = recipe.run_with(&mut unpacker).unwrap();
+        assert_eq!(*started.lock().unwrap(), vec![(2, 4)]);
+        assert_eq!(summary.build, Some(TEST_BUILD));
+        assert_eq!(summary.extracted, vec![2]);
+        assert_eq!(summary.excluded, 1);
+        assert_eq!(summary.bytes, 4);
+        assert!(summary.params.is_none());
+        assert_eq!(
+            std::fs::read(dest.join("gui/4k/logo.png")).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert!(dest.join("gui/dogTags/medium/tag.png").exists());
+        assert!(!dest.join("gui/4k/logo.dds").exists());
+    }
+
+    #[test]
+    fn test_strip_dir() {
+        assert_eq!(strip_dir("gui/4k/logo.png", "GUI/"), "4k/logo.png");
+        assert_eq!(strip_dir("gui/4k/logo.png", "gui/4k"), "logo.png");
+        assert_eq!(strip_dir("gui_old/logo.png", "gui"), "gui_old/logo.png");
+        assert_eq!(strip_dir("gui/logo.png", ""), "gui/logo.png");
+    }
+
+    #[test]
+    fn test_parse_recipe() {
+        let recipe = Recipe::from_json(
+            r#"{"server": "pt", "build": 12, "lang": {"languages": ["en", "pt-br"], "merged": true}, "params": {"format": "cbor"}}"#,
+        )
+        .unwrap();
+        assert_eq!(recipe.dest, "output");
+        assert_eq!(recipe.build, Some(12));
+        let lang = recipe.lang.unwrap();
+        assert_eq!(
+            lang.languages,
+            vec![GameLanguages::EN, GameLanguages::PT_BR]
+        );
+        assert_eq!(recipe.params.unwrap().format.extension(), "cbor");
+
+        assert!(Recipe::from_json(r#"{"server": "eu"}"#).is_err());
+        assert!(Recipe::from_toml("unknown = 1").is_err());
+    }
+}
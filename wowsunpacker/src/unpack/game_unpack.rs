@@ -1,14 +1,22 @@
 use crate::types::{UnpackError, UnpackResult};
+use crate::unpack::game_event::{EventCallback, UnpackEvent};
+use crate::unpack::game_export::IndexFormat;
+use crate::unpack::game_recipe::{Recipe, RecipeSummary};
+use crate::unpack::params_decode::ParamsDecoder;
+use crate::unpack::params_format::ParamsFormat;
 use crate::utils::functions::{read_string, write_file_data};
 use crate::utils::game::GameLanguages;
 use flate2::bufread::DeflateDecoder;
 use log::{debug, error, info, warn};
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+// the packed path of the game params
+pub const GAME_PARAMS_PATH: &str = "content/GameParams.data";
 
 // the index file header
 const G_IDX_SIGNATURE: [u8; 4] = [0x49, 0x53, 0x46, 0x50];
@@ -98,14 +106,65 @@ impl Node {
 }
 
 const FILE_RECORD_SIZE: u32 = 48;
-#[derive(Debug, Clone)]
-struct FileRecord {
-    pkg_name: String,
-    path: String,
-    id: u64,
-    offset: i64,
-    size: i32,
-    uncompressed_size: i64,
+/// Where the game loads a file from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSource {
+    /// Packed in a pkg file of res_packages
+    #[default]
+    Pkg,
+    /// A loose file of bin/build/res_mods, the game loads it instead of a packed file
+    ResMods,
+}
+
+impl FileSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileSource::Pkg => "pkg",
+            FileSource::ResMods => "res_mods",
+        }
+    }
+}
+
+/// Where a packed file is stored, size is the compressed size in the pkg file
+/// Files of res_mods have no pkg_name, id and offset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileRecord {
+    pub pkg_name: String,
+    pub path: String,
+    pub id: u64,
+    pub offset: i64,
+    pub size: i32,
+    pub uncompressed_size: i64,
+    pub source: FileSource,
+    /// The path under res_mods when its case differs from the packed path it replaces
+    #[serde(skip)]
+    loose_path: Option<String>,
+}
+
+/// A path of the directory tree and where its file is loaded from
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct ResolvedFile {
+    pub path: String,
+    pub source: FileSource,
+}
+
+impl ResolvedFile {
+    fn new(file_record: &FileRecord) -> Self {
+        Self {
+            path: file_record.path.clone(),
+            source: file_record.source,
+        }
+    }
+}
+
+impl std::fmt::Display for ResolvedFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.source {
+            FileSource::Pkg => write!(f, "{}", self.path),
+            FileSource::ResMods => write!(f, "{} ({})", self.path, self.source.name()),
+        }
+    }
 }
 
 impl FileRecord {
@@ -142,6 +201,8 @@ impl FileRecord {
             offset,
             size,
             uncompressed_size,
+            source: FileSource::Pkg,
+            loose_path: None,
         });
     }
 }
@@ -149,7 +210,8 @@ impl FileRecord {
 struct IdxFile {
     pkg_name: String,
     // nodes: HashMap<u64, Node>,
-    files: HashMap<String, FileRecord>,
+    /// In the order of the idx file, a path may be there more than once
+    files: Vec<FileRecord>,
 }
 
 impl IdxFile {
@@ -224,7 +286,7 @@ impl IdxFile {
             return None;
         }
 
-        let mut files: HashMap<String, FileRecord> = HashMap::new();
+        let mut files = Vec::with_capacity(header_files);
         for i in 0..header_files {
             let index = i * file_record_size;
             let file_record_data = &file_record_data[index..index + file_record_size];
@@ -235,9 +297,8 @@ impl IdxFile {
             }
             let file_record = file_record.unwrap();
             debug!("FileRecord: {:?}", file_record);
-            files.insert(file_record.path.clone(), file_record);
+            files.push(file_record);
         }
-        assert_eq!(files.len(), header_files);
 
         // parse trailer
         let trailer_offset = header.trailer_offset as usize + 0x10;
@@ -269,85 +330,465 @@ impl IdxFile {
     }
 }
 
-struct TreeNode {
-    nodes: HashMap<String, TreeNode>,
-    file: Option<FileRecord>,
+/// Directories are keyed by their name, files are leaves keyed by their file name
+enum TreeNode {
+    Directory(HashMap<String, TreeNode>),
+    File(FileRecord),
 }
 
 impl TreeNode {
-    fn new() -> Self {
-        Self {
-            nodes: HashMap::new(),
-            file: None,
+    fn record(&self) -> Option<&FileRecord> {
+        match self {
+            TreeNode::File(record) => Some(record),
+            TreeNode::Directory(_) => None,
         }
     }
 
-    fn create_with(file: FileRecord) -> Self {
-        Self {
-            nodes: HashMap::new(),
-            file: Some(file),
+    /// The files and directories below this node, sorted by name
+    fn children(&self) -> Vec<(&String, &TreeNode)> {
+        let mut children: Vec<(&String, &TreeNode)> = match self {
+            TreeNode::Directory(nodes) => nodes.iter().collect(),
+            TreeNode::File(_) => Vec::new(),
+        };
+        children.sort_by(|a, b| a.0.cmp(b.0));
+        children
+    }
+
+    fn entry(&self, path: &str) -> IndexEntry {
+        let record = self.record().cloned();
+        IndexEntry {
+            path: path.to_string(),
+            name: path.rsplit('/').next().unwrap_or_default().to_string(),
+            is_dir: record.is_none(),
+            record,
         }
     }
 }
 
+/// A file or a directory of the packed files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexEntry {
+    /// The full path without a trailing slash, the root is empty
+    pub path: String,
+    pub name: String,
+    pub is_dir: bool,
+    /// None for directories
+    pub record: Option<FileRecord>,
+}
+
+/// Visit every file and directory below a path, parents come before their children
+pub struct TreeWalk<'a> {
+    stack: Vec<(String, &'a TreeNode)>,
+}
+
+impl<'a> TreeWalk<'a> {
+    fn push_children(&mut self, path: &str, node: &'a TreeNode) {
+        // reversed, so the first child is popped first
+        for (name, child) in node.children().into_iter().rev() {
+            self.stack.push((join_path(path, name), child));
+        }
+    }
+}
+
+impl<'a> Iterator for TreeWalk<'a> {
+    type Item = IndexEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.stack.pop()?;
+        let entry = node.entry(&path);
+        if entry.is_dir {
+            self.push_children(&path, node);
+        }
+        Some(entry)
+    }
+}
+
 struct DirectoryTree {
     root: TreeNode,
+    /// The lowercase paths of every file and directory, only kept for case-insensitive lookups
+    lowercase: Option<HashMap<String, String>>,
+    /// Records which were not inserted as their path was already there
+    shadowed: Vec<ShadowedRecord>,
 }
 
 impl DirectoryTree {
-    fn find(&self, path: &str) -> Option<&TreeNode> {
+    fn new() -> Self {
+        Self {
+            root: TreeNode::Directory(HashMap::new()),
+            lowercase: None,
+            shadowed: Vec::new(),
+        }
+    }
+
+    /// Remove every record, the case-insensitive lookup stays enabled
+    fn clear(&mut self) {
+        let case_insensitive = self.lowercase.is_some();
+        *self = Self::new();
+        self.set_case_insensitive(case_insensitive);
+    }
+
+    fn set_case_insensitive(&mut self, enabled: bool) {
+        self.lowercase = match enabled {
+            true => Some(self.lowercase_index()),
+            false => None,
+        };
+    }
+
+    /// The lowercase paths of the records inserted so far
+    fn lowercase_index(&self) -> HashMap<String, String> {
+        let mut lowercase = HashMap::new();
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            match node {
+                TreeNode::Directory(nodes) => stack.extend(nodes.values()),
+                TreeNode::File(file) => index_path(&mut lowercase, &file.path),
+            }
+        }
+        lowercase
+    }
+
+    /// The node of a normalized path, the root for an empty path
+    fn node(&self, path: &str) -> Option<&TreeNode> {
         let mut current = &self.root;
-        for part in path.split("/") {
+        for part in path.split('/') {
             if part.is_empty() {
                 continue;
             }
 
-            if let Some(node) = current.nodes.get(part) {
-                current = node;
-            } else {
-                return None;
+            match current {
+                TreeNode::Directory(nodes) => current = nodes.get(part)?,
+                // a file has no children
+                TreeNode::File(_) => return None,
             }
         }
         Some(current)
     }
 
-    fn insert(&mut self, file_record: &FileRecord) {
-        if let Some(_) = file_record.path.rfind('/') {
-            // under a directory
-            self.create_path(file_record);
-        } else {
-            // under root
-            self.root.nodes.insert(
-                file_record.path.clone(),
-                TreeNode::create_with(file_record.clone()),
-            );
+    /// The node of a file or a directory and its path as stored in the tree, see normalize_path()
+    fn find(&self, path: &str) -> Option<(String, &TreeNode)> {
+        let path = normalize_path(path);
+        if let Some(node) = self.node(&path) {
+            return Some((path, node));
         }
+
+        let actual = self.lowercase.as_ref()?.get(&path.to_lowercase())?;
+        Some((actual.clone(), self.node(actual)?))
     }
 
-    /// Add the file record to the directory tree
-    /// Create the path if it doesn't exist
-    fn create_path(&mut self, file_record: &FileRecord) {
-        let mut current = &mut self.root;
-        for part in file_record.path.split("/") {
-            if part.is_empty() {
-                continue;
+    /**
+     * Find the paths closest to a missing path, below its deepest existing directory
+     * @param path The missing path
+     * @param limit The maximum number of suggestions
+     * @return The suggestions, the closest first
+     */
+    fn suggest(&self, path: &str, limit: usize) -> Vec<String> {
+        let mut parent = (String::new(), &self.root);
+        let mut missing = None;
+        for part in normalize_path(path).split('/') {
+            match self.find(&join_path(&parent.0, part)) {
+                Some(found) => parent = found,
+                None => {
+                    missing = Some(part.to_lowercase());
+                    break;
+                }
+            }
+        }
+        let missing = match missing {
+            Some(missing) if !missing.is_empty() => missing,
+            _ => return Vec::new(),
+        };
+
+        // allow a typo every three characters, at least one
+        let max_distance = (missing.chars().count() / 3).max(1);
+        let mut candidates: Vec<(usize, String)> = parent
+            .1
+            .children()
+            .into_iter()
+            .map(|(name, _)| (edit_distance(&missing, &name.to_lowercase()), name))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .map(|(distance, name)| (distance, join_path(&parent.0, name)))
+            .collect();
+        candidates.sort();
+        candidates
+            .into_iter()
+            .take(limit)
+            .map(|(_, path)| path)
+            .collect()
+    }
+
+    /**
+     * Put a file of res_mods over the packed files, the game matches their paths case-insensitively
+     * @param file_record The loose file, its path takes the case of the packed file or directory it matches
+     * @param packed The lowercase index of the packed paths, see lowercase_index()
+     */
+    fn overlay(&mut self, mut file_record: FileRecord, packed: &HashMap<String, String>) {
+        let path = file_record.path.clone();
+        let mut end = path.len();
+        loop {
+            if let Some(actual) = packed.get(&path[..end].to_lowercase()) {
+                file_record.path = format!("{}{}", actual, &path[end..]);
+                break;
+            }
+            match path[..end].rfind('/') {
+                Some(index) => end = index,
+                None => break,
+            }
+        }
+        if file_record.path != path {
+            file_record.loose_path = Some(path);
+        }
+        self.insert_record(&file_record, true);
+    }
+
+    /// Add the file record to the directory tree, its directories are created if they don't exist
+    /// A record of a path which is already there is shadowed by the existing one
+    fn insert(&mut self, file_record: &FileRecord) {
+        self.insert_record(file_record, false);
+    }
+
+    /**
+     * Add the file record to the directory tree
+     * @param replace Whether the record replaces an existing one instead of being shadowed by it
+     */
+    fn insert_record(&mut self, file_record: &FileRecord, replace: bool) {
+        let parts: Vec<&str> = file_record
+            .path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect();
+        let (name, directories) = match parts.split_last() {
+            Some(split) => split,
+            None => {
+                warn!("Skipping a file record without a path");
+                return;
             }
+        };
+
+        let mut nodes = match &mut self.root {
+            TreeNode::Directory(nodes) => nodes,
+            TreeNode::File(_) => unreachable!("The root is a directory"),
+        };
+        for part in directories {
+            let node = nodes
+                .entry(part.to_string())
+                .or_insert_with(|| TreeNode::Directory(HashMap::new()));
+            nodes = match node {
+                TreeNode::Directory(children) => children,
+                TreeNode::File(file) => {
+                    warn!("Skipping {} as {} is a file", file_record.path, file.path);
+                    return;
+                }
+            };
+        }
 
-            // Insert it if it doesn't exist
-            if !current.nodes.contains_key(part) {
-                let new_node = TreeNode::new();
-                current.nodes.insert(part.to_string(), new_node);
+        match nodes.get_mut(*name) {
+            Some(TreeNode::Directory(_)) => {
+                warn!("Skipping {} as it is a directory", file_record.path);
+                return;
+            }
+            Some(TreeNode::File(existing)) if replace => {
+                debug!(
+                    "{} of {} is replaced by {}",
+                    existing.path,
+                    existing.pkg_name,
+                    file_record.source.name()
+                );
+                self.shadowed.push(ShadowedRecord {
+                    record: existing.clone(),
+                    winner: file_record.clone(),
+                });
+                *existing = file_record.clone();
+                return;
             }
-            current = current
-                .nodes
-                .get_mut(part)
-                .expect("Failed to find the node");
+            Some(TreeNode::File(winner)) => {
+                debug!(
+                    "{} of {} is shadowed by {}",
+                    file_record.path, file_record.pkg_name, winner.pkg_name
+                );
+                self.shadowed.push(ShadowedRecord {
+                    record: file_record.clone(),
+                    winner: winner.clone(),
+                });
+                return;
+            }
+            None => {}
+        }
+        nodes.insert(name.to_string(), TreeNode::File(file_record.clone()));
+        if let Some(lowercase) = &mut self.lowercase {
+            index_path(lowercase, &file_record.path);
+        }
+    }
+}
+
+/// Add a file and its directories to the lowercase index
+fn index_path(lowercase: &mut HashMap<String, String>, path: &str) {
+    let path = normalize_path(path);
+    let mut end = path.len();
+    loop {
+        let prefix = &path[..end];
+        if lowercase.contains_key(&prefix.to_lowercase()) {
+            break;
+        }
+        lowercase.insert(prefix.to_lowercase(), prefix.to_string());
+        match prefix.rfind('/') {
+            Some(index) => end = index,
+            None => break,
+        }
+    }
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    match parent.is_empty() {
+        true => name.to_string(),
+        false => format!("{}/{}", parent, name),
+    }
+}
+
+/// The number of single character edits between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/**
+ * Normalize a packed path, e.g. .\GUI\4k\ becomes GUI/4k
+ * @param path A path with / or \ separators
+ * @return The path without . segments and leading or trailing /, the case is kept
+ */
+pub fn normalize_path(path: &str) -> String {
+    path.split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+/// The regex of a search query, * is a wildcard and the case is ignored
+pub(crate) fn wildcard_regex(query: &str) -> UnpackResult<Regex> {
+    Ok(Regex::new(&format!("(?i){}", query.replace('*', ".*")))?)
+}
+
+/// The languages found under res/texts of a build
+#[derive(Debug)]
+pub struct InstalledLanguages {
+    pub languages: Vec<GameLanguages>,
+    /// folders with a global.mo that don't match any GameLanguages
+    pub unknown: Vec<String>,
+}
+
+/// What to do when a record can't be extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorMode {
+    /// Stop at the first failing record, the rest is skipped
+    FailFast,
+    /// Keep extracting and collect every failure
+    Continue,
+}
+
+/// Which record wins when several idx files contain the same path
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Precedence {
+    /// The idx file whose name sorts first wins
+    #[default]
+    FirstIdx,
+    /// The idx file whose name sorts last wins
+    LastIdx,
+    /// The listed pkg names win in this order, the others follow by idx name
+    PkgOrder(Vec<String>),
+}
+
+impl Precedence {
+    /// Sort parsed idx files by their name so the winning one comes first
+    fn sort(&self, idx_files: &mut [(String, IdxFile)]) {
+        idx_files.sort_by(|a, b| a.0.cmp(&b.0));
+        match self {
+            Precedence::FirstIdx => {}
+            Precedence::LastIdx => idx_files.reverse(),
+            Precedence::PkgOrder(pkg_names) => idx_files.sort_by_key(|(_, idx_file)| {
+                pkg_names
+                    .iter()
+                    .position(|pkg_name| *pkg_name == idx_file.pkg_name)
+                    .unwrap_or(pkg_names.len())
+            }),
         }
+    }
+}
+
+/// A record hidden by another record of the same path, see GameUnpacker::shadowed()
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShadowedRecord {
+    pub record: FileRecord,
+    /// The record in the directory tree
+    pub winner: FileRecord,
+}
+
+/// A record which could not be extracted, with where it was read from
+#[derive(Debug, Serialize)]
+pub struct ExtractFailure {
+    pub path: String,
+    pub pkg_name: String,
+    pub offset: i64,
+    pub size: i32,
+    pub error: String,
+}
 
-        current.nodes.insert(
-            file_record.path.clone(),
-            TreeNode::create_with(file_record.clone()),
+#[derive(Debug, Default, Serialize)]
+pub struct ExtractSummary {
+    /// The paths written to the destination
+    pub written: Vec<ResolvedFile>,
+    pub bytes: u64,
+    /// Records not attempted after a failure in ErrorMode::FailFast
+    pub skipped: Vec<String>,
+    pub failures: Vec<ExtractFailure>,
+}
+
+impl ExtractSummary {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn info(&self) -> &Self {
+        info!(
+            "Extracted {} files ({} bytes), {} skipped, {} failed",
+            self.written.len(),
+            self.bytes,
+            self.skipped.len(),
+            self.failures.len()
         );
+        for failure in &self.failures {
+            warn!(
+                "Failed to extract {} from {} at {}: {}",
+                failure.path, failure.pkg_name, failure.offset, failure.error
+            );
+        }
+        self
+    }
+}
+
+/// A file which could not be read back from its pkg file
+#[derive(Debug, Serialize)]
+pub struct VerifyFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub failures: Vec<VerifyFailure>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
     }
 }
 
@@ -356,16 +797,53 @@ pub struct GameUnpacker {
     pkg_path: String,
     idx_path: String,
     text_path: String,
+    build: Option<u32>,
+    observer: Option<EventCallback>,
+    precedence: Precedence,
+    /// bin/build/res_mods next to the idx folder
+    mods_path: String,
+    res_mods: bool,
 }
 
 impl GameUnpacker {
     pub fn auto(game_path: &str) -> UnpackResult<Self> {
+        // need to find the latest index folder
+        let builds = Self::builds(game_path)?;
+        let latest_build = builds.first().ok_or("Failed to find idx folder")?;
+        Self::with_build(game_path, *latest_build)
+    }
+
+    /**
+     * Create a new Unpacker for a specific build under game_path/bin
+     * @param game_path The game directory
+     * @param build The build number, see builds()
+     */
+    pub fn with_build(game_path: &str, build: u32) -> UnpackResult<Self> {
         let pkg_path = Path::new(game_path).join("res_packages");
         if !pkg_path.exists() {
             return Err(Box::from("Failed to find res_packages directory"));
         }
 
-        // need to find the latest index folder
+        // game_path/bin/build
+        let idx_path = Path::new(game_path)
+            .join("bin")
+            .join(build.to_string())
+            .join("idx");
+        let idx_path = idx_path.to_str().ok_or("Failed to get idx path")?;
+        let pkg_path = pkg_path.to_str().ok_or("Failed to get pkg path")?;
+
+        info!("Idx path: {}", idx_path);
+        info!("Pkg path: {}", pkg_path);
+
+        Self::manual(pkg_path, idx_path)
+    }
+
+    /**
+     * Get all builds of the game with an idx folder, the latest build comes first
+     * @param game_path The game directory
+     * @return The build numbers under game_path/bin
+     */
+    pub fn builds(game_path: &str) -> UnpackResult<Vec<u32>> {
         let bin_path = Path::new(game_path).join("bin");
         if !bin_path.exists() {
             return Err(Box::from("Failed to find bin directory"));
@@ -388,36 +866,19 @@ impl GameUnpacker {
             }
         }
 
-        let mut latest_folder: Option<String> = None;
         // starting from the latest, we need to validate it
         // (a newer folder may be there for faster updates, but it is not complete)
         folder_numebers.sort();
         folder_numebers.reverse();
-        for folder_num in folder_numebers {
-            let folder_name = folder_num.to_string();
-            let idx_path = Path::new(game_path)
+        folder_numebers.retain(|folder_num| {
+            Path::new(game_path)
                 .join("bin")
-                .join(&folder_name)
-                .join("idx");
-            if idx_path.exists() {
-                latest_folder = Some(folder_name.to_string());
-                break;
-            }
-        }
-
-        let latest_folder = latest_folder.ok_or("Failed to find idx folder")?;
-        // game_path/bin/latest_folder
-        let idx_path = Path::new(game_path)
-            .join("bin")
-            .join(latest_folder)
-            .join("idx");
-        let idx_path = idx_path.to_str().ok_or("Failed to get idx path")?;
-        let pkg_path = pkg_path.to_str().ok_or("Failed to get pkg path")?;
-
-        info!("Idx path: {}", idx_path);
-        info!("Pkg path: {}", pkg_path);
+                .join(folder_num.to_string())
+                .join("idx")
+                .exists()
+        });
 
-        return Self::manual(pkg_path, idx_path);
+        Ok(folder_numebers)
     }
 
     /**
@@ -437,27 +898,95 @@ impl GameUnpacker {
         }
 
         let text_path = idx_path.replace("idx", "res/texts");
+        // the idx folder is under bin/build
+        let build = Path::new(idx_path)
+            .parent()
+            .and_then(|build_path| build_path.file_name())
+            .and_then(|build| build.to_str())
+            .and_then(|build| build.parse::<u32>().ok());
+        let mods_path = Path::new(idx_path)
+            .parent()
+            .ok_or("Failed to get the build folder")?
+            .join("res_mods");
         Ok(GameUnpacker {
-            directory_tree: DirectoryTree {
-                root: TreeNode::new(),
-            },
+            directory_tree: DirectoryTree::new(),
             pkg_path: pkg_path.to_string(),
             idx_path: idx_path.to_string(),
             text_path,
+            build,
+            observer: None,
+            precedence: Precedence::default(),
+            mods_path: mods_path
+                .to_str()
+                .ok_or("Invalid res_mods path")?
+                .to_string(),
+            res_mods: false,
         })
     }
 
+    /**
+     * Receive the events of this unpacker, e.g. to show the progress of an extraction
+     * @param observer Called with every event, errors are still returned by the failing call
+     */
+    pub fn with_observer(
+        mut self,
+        observer: impl Fn(&UnpackEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /**
+     * Look up paths ignoring their case, e.g. GUI/4K finds gui/4k
+     * @param enabled Keeps an index of the lowercase paths, exact matches are still preferred
+     */
+    pub fn with_case_insensitive_lookup(mut self, enabled: bool) -> Self {
+        self.directory_tree.set_case_insensitive(enabled);
+        self
+    }
+
+    /**
+     * Choose which record wins when several idx files contain the same path
+     * @param precedence Used by the next build_directory_tree()
+     */
+    pub fn with_precedence(mut self, precedence: Precedence) -> Self {
+        self.precedence = precedence;
+        self
+    }
+
+    /**
+     * Overlay the loose files of bin/build/res_mods on the packed files, like the game loads them
+     * @param enabled Used by the next build_directory_tree(), see overridden()
+     */
+    pub fn with_res_mods(mut self, enabled: bool) -> Self {
+        self.res_mods = enabled;
+        self
+    }
+
+    fn emit(&self, event: UnpackEvent) {
+        if let Some(observer) = &self.observer {
+            observer(&event);
+        }
+    }
+
+    /// The build number of the idx folder, None if it is not under bin/build
+    pub fn build(&self) -> Option<u32> {
+        self.build
+    }
+
+    /// Parse every idx file into the directory tree, duplicate paths are resolved by the precedence
     pub fn build_directory_tree(&mut self) -> UnpackResult<&Self> {
+        let mut idx_files = Vec::new();
         for entry in std::fs::read_dir(self.idx_path.to_string())? {
             let entry = entry?;
             let path = entry.path();
             if path.is_file() && path.extension().unwrap() == "idx" {
-                let filename = path.clone();
-                let filename = filename
+                let filename = path
                     .file_name()
                     .ok_or("Failed to get filename")?
                     .to_str()
-                    .ok_or("Failed to convert filename to str")?;
+                    .ok_or("Failed to convert filename to str")?
+                    .to_string();
                 info!("Parsing idx file: {}", filename);
 
                 // read with buffer to speed up
@@ -467,77 +996,345 @@ impl GameUnpacker {
 
                 let idx_file = IdxFile::parse(&data).ok_or("Failed to parse idx file")?;
                 info!("Parsed idx file: {}", filename);
-                for (path, file_record) in idx_file.files {
-                    self.directory_tree.insert(&FileRecord {
-                        pkg_name: idx_file.pkg_name.clone(),
-                        path,
-                        id: file_record.id,
-                        offset: file_record.offset,
-                        size: file_record.size,
-                        uncompressed_size: file_record.uncompressed_size,
-                    });
-                }
+                self.emit(UnpackEvent::IndexParsed {
+                    idx_name: &filename,
+                    files: idx_file.files.len(),
+                });
+                idx_files.push((filename, idx_file));
+            }
+        }
+
+        // the first record of a path wins, so insert in the order of precedence
+        self.precedence.sort(&mut idx_files);
+        self.directory_tree.clear();
+        for (_, idx_file) in idx_files {
+            for mut file_record in idx_file.files {
+                file_record.pkg_name = idx_file.pkg_name.clone();
+                self.directory_tree.insert(&file_record);
+            }
+        }
+        if self.res_mods {
+            let mod_records = self.mod_records()?;
+            info!("Found {} files in {}", mod_records.len(), self.mods_path);
+            let packed = self.directory_tree.lowercase_index();
+            for file_record in mod_records {
+                self.directory_tree.overlay(file_record, &packed);
             }
         }
 
+        let shadowed = &mut self.directory_tree.shadowed;
+        shadowed.sort_by(|a, b| {
+            (&a.record.path, &a.record.pkg_name).cmp(&(&b.record.path, &b.record.pkg_name))
+        });
+        if !shadowed.is_empty() {
+            warn!(
+                "{} records are shadowed by a record of the same path",
+                shadowed.len()
+            );
+        }
+
         Ok(self)
     }
 
+    /// The records hidden by another record of the same path, sorted by path
+    pub fn shadowed(&self) -> &[ShadowedRecord] {
+        &self.directory_tree.shadowed
+    }
+
+    /// The packed records replaced by a file of res_mods, see with_res_mods()
+    pub fn overridden(&self) -> Vec<&ShadowedRecord> {
+        self.shadowed()
+            .iter()
+            .filter(|shadowed| shadowed.winner.source == FileSource::ResMods)
+            .collect()
+    }
+
+    /// Records of every file under res_mods, sorted by path
+    fn mod_records(&self) -> UnpackResult<Vec<FileRecord>> {
+        let mods_path = Path::new(&self.mods_path);
+        let mut records = Vec::new();
+        if !mods_path.is_dir() {
+            return Ok(records);
+        }
+
+        let mut stack = vec![mods_path.to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    stack.push(path);
+                    continue;
+                }
+
+                let relative = path
+                    .strip_prefix(mods_path)?
+                    .to_str()
+                    .ok_or("Invalid res_mods file name")?;
+                let size = path.metadata()?.len();
+                // the record keeps the size like an idx file
+                let size = i32::try_from(size)
+                    .map_err(|_| format!("{} is too large for a file record", path.display()))?;
+                records.push(FileRecord {
+                    pkg_name: String::new(),
+                    path: normalize_path(relative),
+                    id: 0,
+                    offset: 0,
+                    size,
+                    uncompressed_size: size as i64,
+                    source: FileSource::ResMods,
+                    loose_path: None,
+                });
+            }
+        }
+
+        records.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(records)
+    }
+
     pub fn get_lang_path(&self, language: &GameLanguages) -> String {
         let folder = language.to_folder_string();
         return format!("{}/{}/LC_MESSAGES/global.mo", self.text_path, folder);
     }
 
+    /**
+     * Find all languages with a global.mo under res/texts of the current build.
+     * Folders which are not a known GameLanguages are reported as unknown.
+     * @return The installed languages
+     */
+    pub fn installed_languages(&self) -> UnpackResult<InstalledLanguages> {
+        let text_path = Path::new(&self.text_path);
+        if !text_path.exists() {
+            return Err(Box::from(format!(
+                "Failed to find texts directory: {}",
+                self.text_path
+            )));
+        }
+
+        let mut installed = InstalledLanguages {
+            languages: Vec::new(),
+            unknown: Vec::new(),
+        };
+        for entry in std::fs::read_dir(text_path)? {
+            let path = entry?.path();
+            if !path.join("LC_MESSAGES").join("global.mo").is_file() {
+                continue;
+            }
+
+            let folder = path
+                .file_name()
+                .ok_or("Failed to get folder name")?
+                .to_str()
+                .ok_or("Failed to get folder name")?;
+            match folder.parse::<GameLanguages>() {
+                Ok(lang) => installed.languages.push(lang),
+                Err(_) => {
+                    warn!("Unknown language folder: {}", folder);
+                    installed.unknown.push(folder.to_string());
+                }
+            }
+        }
+
+        installed.languages.sort();
+        installed.unknown.sort();
+        info!(
+            "Found {} languages and {} unknown folders",
+            installed.languages.len(),
+            installed.unknown.len()
+        );
+        Ok(installed)
+    }
+
     pub fn extract_exact(&self, node_name: &str, dest: &str) -> UnpackResult<&Self> {
-        let node_result = self.directory_tree.find(node_name);
-        if node_result.is_none() {
-            warn!(
-                "There exists no node with name {} in directory tree",
-                node_name
-            );
-            return Ok(self);
+        let summary = self.extract_node(node_name, dest, ErrorMode::FailFast)?;
+        match summary.failures.into_iter().next() {
+            Some(failure) => Err(Box::from(failure.error)),
+            None => Ok(self),
         }
+    }
 
-        // extract the node
-        let root_node = node_result.ok_or("Failed to find node")?;
+    pub fn extract_fuzzy(&self, query: &str, dest: &str) -> UnpackResult<&Self> {
+        let summary = self.extract_matching(query, dest, ErrorMode::FailFast)?;
+        match summary.failures.into_iter().next() {
+            Some(failure) => Err(Box::from(failure.error)),
+            None => Ok(self),
+        }
+    }
+
+    /**
+     * Extract a directory or a file, failing records are reported in the summary instead of an error
+     * @param node_name The path in the directory tree, e.g. gui/4k/
+     * @param dest The destination path
+     * @param mode Whether to stop at the first failing record
+     * @return The written, skipped and failed records
+     */
+    pub fn extract_node(
+        &self,
+        node_name: &str,
+        dest: &str,
+        mode: ErrorMode,
+    ) -> UnpackResult<ExtractSummary> {
+        // collect the records first so the totals are known
+        let (_, root_node) = self.find_node(node_name)?;
+        let mut records = Vec::new();
         let mut stack = vec![root_node];
         while !stack.is_empty() {
             let node = stack.pop().ok_or("Failed to pop node from stack")?;
-            for (_, child) in &node.nodes {
-                stack.push(child);
+            match node {
+                TreeNode::Directory(nodes) => stack.extend(nodes.values()),
+                TreeNode::File(file) => records.push(file),
             }
-
-            // make sure the record is valid
-            if node.file.is_none() {
-                continue;
-            }
-
-            let file = node.file.as_ref().ok_or("Failed to get file record ref")?;
-            self.extract_file(file, dest)?;
         }
 
-        Ok(self)
+        Ok(self.extract_records(records, dest, mode))
     }
 
-    pub fn extract_fuzzy(&self, query: &str, dest: &str) -> UnpackResult<&Self> {
+    /**
+     * Extract all files matching a search query, see search() and extract_node()
+     * @param query The search query
+     * @param dest The destination path
+     * @param mode Whether to stop at the first failing record
+     * @return The written, skipped and failed records
+     */
+    pub fn extract_matching(
+        &self,
+        query: &str,
+        dest: &str,
+        mode: ErrorMode,
+    ) -> UnpackResult<ExtractSummary> {
+        let mut records = Vec::new();
         self.matches(query, &mut |file_record| {
-            self.extract_file(file_record, dest)?;
+            records.push(file_record.clone());
             Ok(())
         })?;
 
-        Ok(self)
+        Ok(self.extract_records(records.iter().collect(), dest, mode))
+    }
+
+    fn extract_records(
+        &self,
+        mut records: Vec<&FileRecord>,
+        dest: &str,
+        mode: ErrorMode,
+    ) -> ExtractSummary {
+        // the tree has no order, keep the summary stable between runs
+        records.sort_by(|a, b| a.path.cmp(&b.path));
+        self.emit(UnpackEvent::ExtractStarted {
+            files: records.len(),
+            bytes: records
+                .iter()
+                .map(|record| record.uncompressed_size as u64)
+                .sum(),
+        });
+
+        let mut summary = ExtractSummary::default();
+        for file_record in records {
+            if mode == ErrorMode::FailFast && !summary.is_ok() {
+                summary.skipped.push(file_record.path.clone());
+                continue;
+            }
+
+            let file_path = Path::new(dest).join(&file_record.path);
+            match self.write_record(file_record, &file_path) {
+                Ok(bytes) => {
+                    summary.written.push(ResolvedFile::new(file_record));
+                    summary.bytes += bytes;
+                }
+                Err(err) => {
+                    let error = err.to_string();
+                    self.emit(UnpackEvent::Error {
+                        path: &file_record.path,
+                        error: &error,
+                    });
+                    summary.failures.push(ExtractFailure {
+                        path: file_record.path.clone(),
+                        pkg_name: file_record.pkg_name.clone(),
+                        offset: file_record.offset,
+                        size: file_record.size,
+                        error,
+                    });
+                }
+            }
+        }
+        summary
     }
 
     /**
      * Extract a file_record from the pkg file
      * @param file_record The file record
-     * @param dest The destination path
-     * @return true if success
+     * @param file_path The output file
+     * @return The number of bytes written
+     */
+    fn write_record(&self, file_record: &FileRecord, file_path: &Path) -> UnpackResult<u64> {
+        // remove the filename
+        let out_dir = file_path.parent().ok_or("Failed to get parent dir")?;
+        if !out_dir.exists() {
+            std::fs::create_dir_all(out_dir)?;
+            debug!("Created directory: {}", out_dir.display());
+        }
+
+        // get the output path ready
+        let file_path = file_path.to_str().ok_or("Failed to convert path to str")?;
+        debug!(
+            "Unpacking file: {} ({}/{})",
+            file_path, file_record.size, file_record.uncompressed_size
+        );
+        self.emit(UnpackEvent::RecordStarted {
+            path: &file_record.path,
+            size: file_record.uncompressed_size as u64,
+        });
+        let data = self.read_record(file_record)?;
+        write_file_data(file_path, &data)?;
+        self.emit(UnpackEvent::RecordFinished {
+            path: &file_record.path,
+            bytes: data.len() as u64,
+        });
+        Ok(data.len() as u64)
+    }
+
+    /**
+     * Extract files to any output paths, e.g. to map them into another folder
+     * @param files The records with their output file, the first failure stops the extraction
+     * @return The number of bytes written
+     */
+    pub(crate) fn extract_to(&self, files: &[(&FileRecord, PathBuf)]) -> UnpackResult<u64> {
+        self.emit(UnpackEvent::ExtractStarted {
+            files: files.len(),
+            bytes: files
+                .iter()
+                .map(|(record, _)| record.uncompressed_size as u64)
+                .sum(),
+        });
+
+        let mut bytes = 0;
+        for (file_record, file_path) in files {
+            match self.write_record(file_record, file_path) {
+                Ok(written) => bytes += written,
+                Err(err) => {
+                    self.emit(UnpackEvent::Error {
+                        path: &file_record.path,
+                        error: &err.to_string(),
+                    });
+                    return Err(err);
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    /**
+     * Read the content of a file_record from the pkg file
+     * @param file_record The file record
+     * @return The decompressed data
      */
-    fn extract_file(&self, file_record: &FileRecord, dest: &str) -> UnpackResult<()> {
-        info!("Extracting record: {:?}", file_record);
+    fn read_record(&self, file_record: &FileRecord) -> UnpackResult<Vec<u8>> {
+        debug!("Reading record: {:?}", file_record);
+        if file_record.source == FileSource::ResMods {
+            let loose_path = file_record.loose_path.as_ref().unwrap_or(&file_record.path);
+            return Ok(std::fs::read(Path::new(&self.mods_path).join(loose_path))?);
+        }
+
         let pkg_file_path = Path::new(&self.pkg_path).join(&file_record.pkg_name);
-        info!("Pkg file path: {}", pkg_file_path.display());
+        debug!("Pkg file path: {}", pkg_file_path.display());
         let pkg_file = File::open(pkg_file_path)?;
 
         let pkg_file_size = pkg_file.metadata()?.len() as usize;
@@ -551,60 +1348,161 @@ impl GameUnpacker {
             )));
         }
 
-        // remove the filename
-        let out_dir = Path::new(dest).join(&file_record.path);
-        let out_dir = out_dir.parent().ok_or("Failed to get parent dir")?;
-        if !out_dir.exists() {
-            std::fs::create_dir_all(out_dir)?;
-            println!("Created directory: {}", out_dir.display());
-        }
-        info!("Extracting file: {}", file_record.path);
-
         // go to the file offset
         let mut pkg_reader = BufReader::new(&pkg_file);
         pkg_reader.seek(SeekFrom::Start(file_offset as u64))?;
         let mut raw_data = vec![0; file_size];
         pkg_reader.read_exact(&mut raw_data)?;
 
-        // get the output path ready
-        let file_path = Path::new(dest).join(&file_record.path);
-        let file_path = file_path.to_str().ok_or("Failed to convert path to str")?;
         let file_uncompressed_size = file_record.uncompressed_size as usize;
-        println!(
-            "Unpacking file: {} ({}/{})",
-            file_path, file_size, file_uncompressed_size
-        );
         // decompress if necessary with zlib
         if file_size != file_uncompressed_size {
-            let mut decompressed_data = vec![0; file_uncompressed_size];
+            let mut decompressed_data = Vec::with_capacity(file_uncompressed_size);
             let mut decompressor = DeflateDecoder::new(raw_data.as_slice());
-            decompressor.read(&mut decompressed_data)?;
+            decompressor.read_to_end(&mut decompressed_data)?;
 
             if decompressed_data.len() != file_uncompressed_size {
-                panic!(
+                return Err(Box::from(format!(
                     "Decompressed size ({}) does not match expected size ({})",
                     decompressed_data.len(),
                     file_uncompressed_size
-                );
+                )));
+            }
+            return Ok(decompressed_data);
+        }
+
+        Ok(raw_data)
+    }
+
+    /**
+     * Read a file from the pkg files into memory
+     * @param path The full path of the file, e.g. content/GameParams.data
+     * @return The decompressed file content
+     */
+    pub fn read(&self, path: &str) -> UnpackResult<Vec<u8>> {
+        self.read_record(self.find_record(path)?)
+    }
+
+    fn find_record(&self, path: &str) -> UnpackResult<&FileRecord> {
+        let (path, node) = self.find_node(path)?;
+        Ok(node.record().ok_or(format!("{} is not a file", path))?)
+    }
+
+    /// The node and its path as stored in the tree, a missing path suggests close matches
+    fn find_node(&self, path: &str) -> UnpackResult<(String, &TreeNode)> {
+        match self.directory_tree.find(path) {
+            Some(found) => Ok(found),
+            None => {
+                let suggestions = self.directory_tree.suggest(path, 3);
+                match suggestions.is_empty() {
+                    true => Err(Box::from(format!("Failed to find {}", path))),
+                    false => Err(Box::from(format!(
+                        "Failed to find {}, did you mean {}?",
+                        path,
+                        suggestions.join(", ")
+                    ))),
+                }
             }
-            return write_file_data(file_path, &decompressed_data);
+        }
+    }
+
+    /**
+     * Get the files and directories directly under a directory
+     * @param path The directory, e.g. gui/4k, the root when empty
+     * @return The entries sorted by name
+     */
+    pub fn list(&self, path: &str) -> UnpackResult<Vec<IndexEntry>> {
+        let (path, node) = self.find_node(path)?;
+        if node.record().is_some() {
+            return Err(Box::from(format!("{} is not a directory", path)));
         }
 
-        return write_file_data(file_path, &raw_data);
+        let mut walk = TreeWalk { stack: Vec::new() };
+        walk.push_children(&path, node);
+        Ok(walk
+            .stack
+            .into_iter()
+            .rev()
+            .map(|(path, node)| node.entry(&path))
+            .collect())
+    }
+
+    /**
+     * Get the record of a file or whether a path is a directory
+     * @param path The full path, e.g. content/GameParams.data
+     */
+    pub fn stat(&self, path: &str) -> UnpackResult<IndexEntry> {
+        let (path, node) = self.find_node(path)?;
+        Ok(node.entry(&path))
+    }
+
+    /**
+     * Lazily walk everything below a path, directories are yielded before their content
+     * @param path The directory to walk, the root when empty, a file yields only itself
+     */
+    pub fn walk(&self, path: &str) -> UnpackResult<TreeWalk<'_>> {
+        let (path, node) = self.find_node(path)?;
+        let mut walk = TreeWalk { stack: Vec::new() };
+        if node.record().is_some() {
+            walk.stack.push((path, node));
+        } else {
+            walk.push_children(&path, node);
+        }
+        Ok(walk)
+    }
+
+    /// Read content/GameParams.data and decode it in memory
+    pub fn decode_params(&self) -> UnpackResult<serde_json::Value> {
+        let data = self.read(GAME_PARAMS_PATH)?;
+        ParamsDecoder::decode(&data)
+    }
+
+    /**
+     * Decode content/GameParams.data and write it as json
+     * @param file_path The output json file
+     * @param compact Whether to skip pretty printing
+     */
+    pub fn write_params(&self, file_path: &str, compact: bool) -> UnpackResult<&Self> {
+        self.write_params_as(file_path, ParamsFormat::Json, compact)
+    }
+
+    /**
+     * Decode content/GameParams.data and write it as json, MessagePack or CBOR
+     * @param file_path The output file
+     * @param format The output format
+     * @param compact Whether to skip pretty printing, only used by json
+     */
+    pub fn write_params_as(
+        &self,
+        file_path: &str,
+        format: ParamsFormat,
+        compact: bool,
+    ) -> UnpackResult<&Self> {
+        let params = self.decode_params()?;
+        format.write(&params, file_path, compact)?;
+        Ok(self)
+    }
+
+    /// Run an extraction recipe on this build, see Recipe
+    pub fn run_recipe(&mut self, recipe: &Recipe) -> UnpackResult<RecipeSummary> {
+        recipe.run_with(self)
     }
 
     /**
      * Search all matching files in the directory tree.
      * The search is case insensitive and will treat * as a wildcard.
      * @param query The query string
-     * @return A list of matching files
+     * @return A list of matching files with where they are loaded from
      */
-    pub fn search(&self, query: &str, write_to_disk: bool) -> UnpackResult<Vec<String>> {
+    pub fn search(&self, query: &str, write_to_disk: bool) -> UnpackResult<Vec<ResolvedFile>> {
         let mut results = vec![];
-        let mut file = File::create("search_results.txt").unwrap();
+        let mut file = match write_to_disk {
+            true => Some(File::create("search_results.txt")?),
+            false => None,
+        };
         self.matches(query, &mut |file_record| {
-            results.push(file_record.path.clone());
-            if write_to_disk {
+            results.push(ResolvedFile::new(file_record));
+            if let Some(file) = file.as_mut() {
                 writeln!(file, "{}", file_record.path)?;
             }
             Ok(())
@@ -613,6 +1511,67 @@ impl GameUnpacker {
         Ok(results)
     }
 
+    /**
+     * Write every record of the directory tree with the build number
+     * @param file_path The output file
+     * @param format JSON lines, CSV or SQLite
+     * @return The number of records
+     */
+    pub fn export_index(&self, file_path: &str, format: IndexFormat) -> UnpackResult<usize> {
+        let records: Vec<FileRecord> = self.walk("")?.filter_map(|entry| entry.record).collect();
+        format.write(&records, self.build, file_path)
+    }
+
+    /**
+     * Write the records of the files matching a search query, see search()
+     * @param query The search query
+     * @param file_path The output file
+     * @param format JSON lines, CSV or SQLite
+     * @return The number of records
+     */
+    pub fn export_search(
+        &self,
+        query: &str,
+        file_path: &str,
+        format: IndexFormat,
+    ) -> UnpackResult<usize> {
+        let mut records = Vec::new();
+        self.matches(query, &mut |file_record| {
+            records.push(file_record.clone());
+            Ok(())
+        })?;
+        records.sort_by(|a, b| a.path.cmp(&b.path));
+        format.write(&records, self.build, file_path)
+    }
+
+    /**
+     * Read back every matching file and check it against its record, e.g. after a partial update
+     * @param query The search query, see search()
+     * @return The number of checked files and the files which failed
+     */
+    pub fn verify(&self, query: &str) -> UnpackResult<VerifyReport> {
+        let mut report = VerifyReport::default();
+        self.matches(query, &mut |file_record| {
+            report.checked += 1;
+            if let Err(err) = self.read_record(file_record) {
+                warn!("Failed to verify {}: {}", file_record.path, err);
+                report.failures.push(VerifyFailure {
+                    path: file_record.path.clone(),
+                    error: err.to_string(),
+                });
+            }
+            Ok(())
+        })?;
+
+        report.failures.sort_by(|a, b| a.path.cmp(&b.path));
+        info!(
+            "Verified {} files, {} failed",
+            report.checked,
+            report.failures.len()
+        );
+        Ok(report)
+    }
+
     /**
      * Traverse the tree with the given query. Call the callback when there is a match.
      * @param query The search query with regex support
@@ -623,9 +1582,8 @@ impl GameUnpacker {
         query: &str,
         callback: &mut dyn FnMut(&FileRecord) -> Result<(), UnpackError>,
     ) -> UnpackResult<()> {
-        let query = query.replace("*", ".*");
         // don't put this inside the loop as it slows down the search dramatically
-        let regex = Regex::new(&query).unwrap();
+        let regex = wildcard_regex(query)?;
 
         // search from the root
         let mut stack = vec![&self.directory_tree.root];
@@ -635,20 +1593,17 @@ impl GameUnpacker {
                 continue;
             }
 
-            let current = current.unwrap();
-            for (_, child) in &current.nodes {
-                stack.push(child);
-            }
-
-            if current.file.is_none() {
-                continue;
-            }
-
-            let file_record = current.file.as_ref().unwrap();
+            let file_record = match current.unwrap() {
+                TreeNode::Directory(nodes) => {
+                    stack.extend(nodes.values());
+                    continue;
+                }
+                TreeNode::File(file_record) => file_record,
+            };
             let file_name = &file_record.path;
             // check if the current node matches the query
             if regex.is_match(&file_name.to_lowercase()) {
-                callback(&file_record)?;
+                callback(file_record)?;
             }
         }
 
@@ -702,3 +1657,335 @@ fn test_extract_fuzzy() {
     let result = unpacker.extract_fuzzy("gui/*ap*", "output");
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_verify() {
+    use crate::unpack::test_utils::{unpacker_with, TEST_BUILD};
+
+    let text = "gui text ".repeat(64);
+    let unpacker = unpacker_with(&[(
+        "system_data_0001.pkg",
+        &[
+            ("gui/4k/logo.png", &[1, 2, 3]),
+            ("gui/text.txt", text.as_bytes()),
+            ("content/GameParams.data", &[4, 5, 6, 7]),
+        ],
+    )]);
+    assert_eq!(unpacker.build(), Some(TEST_BUILD));
+    assert_eq!(unpacker.read("gui/text.txt").unwrap(), text.as_bytes());
+
+    let mut results = unpacker.search("gui/*", false).unwrap();
+    results.sort();
+    let paths: Vec<&str> = results.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(paths, vec!["gui/4k/logo.png", "gui/text.txt"]);
+    assert!(results.iter().all(|file| file.source == FileSource::Pkg));
+    let report = unpacker.verify("*").unwrap();
+    assert_eq!(report.checked, 3);
+    assert!(report.is_ok());
+
+    // cut the pkg short, the last file goes past its end
+    let pkg_path = Path::new(&unpacker.pkg_path).join("system_data_0001.pkg");
+    let data = std::fs::read(&pkg_path).unwrap();
+    std::fs::write(&pkg_path, &data[..data.len() - 1]).unwrap();
+    let report = unpacker.verify("*").unwrap();
+    assert_eq!(report.checked, 3);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].path, "content/GameParams.data");
+}
+
+#[test]
+fn test_extract_events() {
+    use crate::unpack::test_utils::{temp_dir, unpacker_with};
+    use std::sync::{Arc, Mutex};
+
+    let unpacker = unpacker_with(&[(
+        "gui_0001.pkg",
+        &[("gui/4k/logo.png", &[1, 2, 3]), ("gui/4k/icon.png", &[4])],
+    )]);
+
+    // the tree is built again to see the index events
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    let mut unpacker = unpacker.with_observer(move |event| {
+        let event = match event {
+            UnpackEvent::IndexParsed { idx_name, files } => {
+                format!("index {} {}", idx_name, files)
+            }
+            UnpackEvent::ExtractStarted { files, bytes } => {
+                format!("start {} {}", files, bytes)
+            }
+            UnpackEvent::RecordFinished { path, bytes } => format!("done {} {}", path, bytes),
+            _ => return,
+        };
+        recorded.lock().unwrap().push(event);
+    });
+    unpacker.build_directory_tree().unwrap();
+    let dest = temp_dir("events");
+    unpacker
+        .extract_exact("gui/4k", dest.to_str().unwrap())
+        .unwrap();
+
+    let mut events = events.lock().unwrap().clone();
+    events[2..].sort();
+    assert_eq!(
+        events,
+        vec![
+            "index gui_0001.idx 2",
+            "start 2 4",
+            "done gui/4k/icon.png 1",
+            "done gui/4k/logo.png 3",
+        ]
+    );
+}
+
+#[test]
+fn test_extract_error_modes() {
+    use crate::unpack::test_utils::{temp_dir, unpacker_with};
+
+    let unpacker = unpacker_with(&[
+        ("gui_0001.pkg", &[("gui/a.png", &[1]), ("gui/c.png", &[3])]),
+        ("gui_0002.pkg", &[("gui/b.png", &[2])]),
+    ]);
+    std::fs::remove_file(Path::new(&unpacker.pkg_path).join("gui_0002.pkg")).unwrap();
+
+    let dest = temp_dir("error_modes");
+    let dest = dest.to_str().unwrap();
+    let written = |summary: &ExtractSummary| -> Vec<String> {
+        summary
+            .written
+            .iter()
+            .map(|file| file.path.clone())
+            .collect()
+    };
+    let summary = unpacker
+        .extract_node("gui", dest, ErrorMode::Continue)
+        .unwrap();
+    assert_eq!(written(&summary), vec!["gui/a.png", "gui/c.png"]);
+    assert_eq!(summary.failures.len(), 1);
+    assert_eq!(summary.failures[0].path, "gui/b.png");
+    assert_eq!(summary.failures[0].pkg_name, "gui_0002.pkg");
+    assert!(summary.skipped.is_empty());
+
+    let summary = unpacker
+        .extract_matching("gui/*", dest, ErrorMode::FailFast)
+        .unwrap();
+    assert_eq!(written(&summary), vec!["gui/a.png"]);
+    assert_eq!(summary.skipped, vec!["gui/c.png"]);
+    assert!(!summary.is_ok());
+
+    assert!(unpacker.extract_exact("gui", dest).is_err());
+    assert!(unpacker.extract_exact("gui/a.png", dest).is_ok());
+}
+
+#[test]
+fn test_list_stat_walk() {
+    use crate::unpack::test_utils::unpacker_with;
+
+    let unpacker = unpacker_with(&[(
+        "gui_0001.pkg",
+        &[
+            ("gui/4k/logo.png", &[1, 2, 3]),
+            ("gui/4k/icon.png", &[4]),
+            ("gui/text.txt", &[5, 6]),
+            ("content/GameParams.data", &[7]),
+        ],
+    )]);
+
+    let names = |entries: Vec<IndexEntry>| -> Vec<String> {
+        entries.into_iter().map(|entry| entry.path).collect()
+    };
+    assert_eq!(names(unpacker.list("").unwrap()), vec!["content", "gui"]);
+    let gui = unpacker.list("gui/").unwrap();
+    assert_eq!(names(gui.clone()), vec!["gui/4k", "gui/text.txt"]);
+    assert!(gui[0].is_dir);
+    assert_eq!(gui[1].record.as_ref().unwrap().uncompressed_size, 2);
+    assert!(unpacker.list("gui/text.txt").is_err());
+    assert!(unpacker.list("sounds").is_err());
+
+    let stat = unpacker.stat("gui/4k/logo.png").unwrap();
+    assert!(!stat.is_dir);
+    assert_eq!(stat.name, "logo.png");
+    let record = stat.record.unwrap();
+    assert_eq!(record.pkg_name, "gui_0001.pkg");
+    assert_eq!(record.path, "gui/4k/logo.png");
+    assert!(unpacker.stat("gui/4k").unwrap().is_dir);
+
+    assert_eq!(
+        names(unpacker.walk("").unwrap().collect()),
+        vec![
+            "content",
+            "content/GameParams.data",
+            "gui",
+            "gui/4k",
+            "gui/4k/icon.png",
+            "gui/4k/logo.png",
+            "gui/text.txt",
+        ]
+    );
+    let files = unpacker.walk("gui").unwrap().filter(|entry| !entry.is_dir);
+    assert_eq!(files.count(), 3);
+    assert_eq!(unpacker.walk("gui/text.txt").unwrap().count(), 1);
+
+    let json = serde_json::to_value(unpacker.stat("gui/text.txt").unwrap()).unwrap();
+    assert_eq!(json["record"]["size"], 2);
+}
+
+#[test]
+fn test_tree_layout() {
+    use crate::unpack::test_utils::unpacker_with;
+
+    let unpacker = unpacker_with(&[(
+        "misc_0001.pkg",
+        &[("readme.txt", &[1, 2]), ("gui/a.png", &[3])],
+    )]);
+
+    // root level files are leaves like every other file
+    let root = unpacker.list("").unwrap();
+    assert_eq!(root.len(), 2);
+    assert_eq!(root[1].path, "readme.txt");
+    assert!(!root[1].is_dir);
+    assert_eq!(unpacker.read("readme.txt").unwrap(), vec![1, 2]);
+    assert_eq!(unpacker.search("*", false).unwrap().len(), 2);
+
+    // files are keyed by their name and have no children
+    let gui = unpacker.list("gui").unwrap();
+    assert_eq!(gui.len(), 1);
+    assert_eq!(gui[0].name, "a.png");
+    assert_eq!(unpacker.read("gui/a.png").unwrap(), vec![3]);
+    assert!(unpacker.list("gui/a.png").is_err());
+    assert!(unpacker.stat("gui/a.png/gui/a.png").is_err());
+    assert!(unpacker.read("gui").is_err());
+}
+
+#[test]
+fn test_lookup_normalized() {
+    use crate::unpack::test_utils::unpacker_with;
+
+    assert_eq!(normalize_path(".\\GUI\\4k\\"), "GUI/4k");
+    assert_eq!(normalize_path("/gui//./4k/logo.png"), "gui/4k/logo.png");
+    assert_eq!(normalize_path("./"), "");
+
+    let unpacker = unpacker_with(&[(
+        "gui_0001.pkg",
+        &[("gui/4k/Logo.png", &[1]), ("gui/dogTags/tag.png", &[2])],
+    )]);
+
+    // separators are normalized but the case has to match
+    assert_eq!(unpacker.read("gui\\4k\\Logo.png").unwrap(), vec![1]);
+    assert_eq!(unpacker.list("./gui/4k/").unwrap().len(), 1);
+    let err = unpacker.read("GUI/4k/Logo.png").unwrap_err().to_string();
+    assert_eq!(err, "Failed to find GUI/4k/Logo.png, did you mean gui?");
+    let err = unpacker.stat("gui/dogtag").unwrap_err().to_string();
+    assert!(err.ends_with("did you mean gui/dogTags?"));
+    let err = unpacker.stat("sounds").unwrap_err().to_string();
+    assert_eq!(err, "Failed to find sounds");
+    assert!(unpacker.extract_exact("gui/missing", "").is_err());
+
+    // the index can be enabled after the tree was built
+    let unpacker = unpacker.with_case_insensitive_lookup(true);
+    let stat = unpacker.stat("GUI\\4K\\logo.PNG").unwrap();
+    assert_eq!(stat.path, "gui/4k/Logo.png");
+    assert_eq!(unpacker.walk("Gui/DOGTAGS").unwrap().count(), 1);
+    assert_eq!(unpacker.search("GUI/4K/*", false).unwrap().len(), 1);
+}
+
+#[test]
+fn test_shadowed_records() {
+    use crate::unpack::test_utils::unpacker_with;
+
+    let unpacker = unpacker_with(&[
+        ("base_0001.pkg", &[("gui/a.png", &[1]), ("gui/b.png", &[2])]),
+        ("patch_0001.pkg", &[("gui/a.png", &[3])]),
+    ]);
+    assert_eq!(unpacker.read("gui/a.png").unwrap(), vec![1]);
+    let shadowed = unpacker.shadowed();
+    assert_eq!(shadowed.len(), 1);
+    assert_eq!(shadowed[0].record.pkg_name, "patch_0001.pkg");
+    assert_eq!(shadowed[0].winner.pkg_name, "base_0001.pkg");
+
+    let mut unpacker = unpacker.with_precedence(Precedence::LastIdx);
+    unpacker.build_directory_tree().unwrap();
+    assert_eq!(unpacker.read("gui/a.png").unwrap(), vec![3]);
+    assert_eq!(unpacker.shadowed()[0].record.pkg_name, "base_0001.pkg");
+    assert_eq!(unpacker.search("*", false).unwrap().len(), 2);
+
+    let precedence = Precedence::PkgOrder(vec!["patch_0001.pkg".to_string()]);
+    let mut unpacker = unpacker.with_precedence(precedence);
+    unpacker.build_directory_tree().unwrap();
+    assert_eq!(
+        unpacker.stat("gui/a.png").unwrap().record.unwrap().pkg_name,
+        "patch_0001.pkg"
+    );
+    assert_eq!(unpacker.read("gui/b.png").unwrap(), vec![2]);
+}
+
+#[test]
+fn test_res_mods() {
+    use crate::unpack::test_utils::{temp_dir, unpacker_with};
+
+    let mut unpacker =
+        unpacker_with(&[("gui_0001.pkg", &[("gui/a.png", &[1]), ("gui/b.png", &[2])])]);
+    // the game matches loose files case-insensitively
+    let mods_path = Path::new(&unpacker.mods_path).join("GUI");
+    std::fs::create_dir_all(mods_path.join("new")).unwrap();
+    std::fs::write(mods_path.join("A.png"), [9, 9]).unwrap();
+    std::fs::write(mods_path.join("new/c.png"), [3]).unwrap();
+
+    // the loose files are ignored unless enabled
+    unpacker.build_directory_tree().unwrap();
+    assert_eq!(unpacker.read("gui/a.png").unwrap(), vec![1]);
+    assert!(unpacker.overridden().is_empty());
+
+    let mut unpacker = unpacker.with_res_mods(true);
+    unpacker.build_directory_tree().unwrap();
+    assert_eq!(unpacker.read("gui/a.png").unwrap(), vec![9, 9]);
+    assert_eq!(unpacker.read("gui/b.png").unwrap(), vec![2]);
+    let record = unpacker.stat("gui/new/c.png").unwrap().record.unwrap();
+    assert_eq!(record.source, FileSource::ResMods);
+    assert_eq!(record.uncompressed_size, 1);
+    assert!(unpacker.stat("GUI").is_err());
+    let mut results = unpacker.search("gui/*", false).unwrap();
+    results.sort();
+    assert_eq!(
+        results[0],
+        ResolvedFile {
+            path: "gui/a.png".to_string(),
+            source: FileSource::ResMods
+        }
+    );
+    assert_eq!(results[1].source, FileSource::Pkg);
+    assert_eq!(results[2].to_string(), "gui/new/c.png (res_mods)");
+
+    let overridden = unpacker.overridden();
+    assert_eq!(overridden.len(), 1);
+    assert_eq!(overridden[0].record.pkg_name, "gui_0001.pkg");
+    assert_eq!(overridden[0].winner.source, FileSource::ResMods);
+    assert_eq!(overridden[0].winner.path, "gui/a.png");
+
+    let dest = temp_dir("mods");
+    let summary = unpacker
+        .extract_node("gui", dest.to_str().unwrap(), ErrorMode::FailFast)
+        .unwrap();
+    assert_eq!(summary.written.len(), 3);
+    assert_eq!(summary.written[0].source, FileSource::ResMods);
+    assert_eq!(std::fs::read(dest.join("gui/a.png")).unwrap(), vec![9, 9]);
+}
+
+#[test]
+fn test_installed_languages() {
+    use crate::unpack::test_utils::unpacker_with;
+
+    let unpacker = unpacker_with(&[("gui_0001.pkg", &[("gui/a.png", &[1])])]);
+    let texts_path = Path::new(&unpacker.text_path);
+    for folder in ["en", "klingon"] {
+        let mo_path = texts_path.join(folder).join("LC_MESSAGES");
+        std::fs::create_dir_all(&mo_path).unwrap();
+        std::fs::write(mo_path.join("global.mo"), []).unwrap();
+    }
+    // a folder without global.mo is not a language
+    std::fs::create_dir_all(texts_path.join("ja")).unwrap();
+
+    let installed = unpacker.installed_languages().unwrap();
+    assert_eq!(installed.languages, vec![GameLanguages::EN]);
+    assert_eq!(installed.unknown, vec!["klingon"]);
+}
@@ -0,0 +1,180 @@
+// Compare the decoded strings of one language between two builds or two installs
+
+use std::{collections::BTreeMap, fmt::Write as _, fs::File, io::Write, path::Path};
+
+use log::info;
+use serde::Serialize;
+
+use crate::types::UnpackResult;
+use crate::unpack::game_unpack::GameUnpacker;
+use crate::unpack::lang_unpack::LangUnpacker;
+use crate::utils::game::GameLanguages;
+
+#[derive(Debug, Serialize)]
+pub struct ChangedString {
+    pub key: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LangDiff {
+    pub added: BTreeMap<String, String>,
+    pub removed: BTreeMap<String, String>,
+    pub changed: Vec<ChangedString>,
+}
+
+impl LangDiff {
+    /**
+     * Decode the same language from two game builds and compare them
+     * @param old The unpacker of the older build or install
+     * @param new The unpacker of the newer build or install
+     * @param language The language to compare
+     */
+    pub fn between(
+        old: &GameUnpacker,
+        new: &GameUnpacker,
+        language: &GameLanguages,
+    ) -> UnpackResult<Self> {
+        let mut old_unpacker = LangUnpacker::new(old.get_lang_path(language))?;
+        old_unpacker.decode()?;
+        let mut new_unpacker = LangUnpacker::new(new.get_lang_path(language))?;
+        new_unpacker.decode()?;
+        Self::compare(&old_unpacker, &new_unpacker)
+    }
+
+    /// Compare two decoded MO files
+    pub fn compare(old: &LangUnpacker, new: &LangUnpacker) -> UnpackResult<Self> {
+        if !old.is_decoded() || !new.is_decoded() {
+            return Err(Box::from(
+                "Text data is not decoded yet, call decode() before comparing",
+            ));
+        }
+
+        let old_data = old.text_data();
+        let new_data = new.text_data();
+        let mut added = BTreeMap::new();
+        let mut removed = BTreeMap::new();
+        let mut changed = Vec::new();
+        for (key, new_value) in new_data {
+            match old_data.get(key) {
+                None => {
+                    added.insert(key.clone(), new_value.clone());
+                }
+                Some(old_value) if old_value != new_value => {
+                    changed.push(ChangedString {
+                        key: key.clone(),
+                        old: old_value.clone(),
+                        new: new_value.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        for (key, old_value) in old_data {
+            if !new_data.contains_key(key) {
+                removed.insert(key.clone(), old_value.clone());
+            }
+        }
+
+        changed.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(Self {
+            added,
+            removed,
+            changed,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn info(&self) -> &Self {
+        info!(
+            "{} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        );
+        self
+    }
+
+    /// A readable report with one line per added, removed or changed string
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(
+            text,
+            "{} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        );
+        for (key, value) in &self.added {
+            let _ = writeln!(text, "+ {}: {}", key, value);
+        }
+        for (key, value) in &self.removed {
+            let _ = writeln!(text, "- {}: {}", key, value);
+        }
+        for change in &self.changed {
+            let _ = writeln!(text, "~ {}: {} -> {}", change.key, change.old, change.new);
+        }
+        text
+    }
+
+    pub fn write_to_file(&self, file_name: &str, dest: &str) -> UnpackResult<()> {
+        let file_path = Path::new(&dest).join(file_name);
+        let mut file = File::create(file_path)?;
+        let json = serde_json::to_string_pretty(&self)?;
+        file.write_all(json.as_bytes())?;
+        info!("Language diff written to {}/{}", dest, file_name);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LangDiff;
+    use crate::unpack::{lang_unpack::LangUnpacker, test_utils::write_mo_file};
+
+    #[test]
+    fn test_lang_diff() {
+        let old = write_mo_file(
+            "diff_old.mo",
+            &[
+                ("IDS_A", "Montana"),
+                ("IDS_B", "Yamato"),
+                ("IDS_C", "Event"),
+            ],
+        );
+        let new = write_mo_file(
+            "diff_new.mo",
+            &[
+                ("IDS_A", "Montana"),
+                ("IDS_B", "Musashi"),
+                ("IDS_D", "Halloween"),
+            ],
+        );
+
+        let mut old = LangUnpacker::new(old).unwrap();
+        let mut new = LangUnpacker::new(new).unwrap();
+        assert!(LangDiff::compare(&old, &new).is_err());
+        old.decode().unwrap();
+        new.decode().unwrap();
+
+        let diff = LangDiff::compare(&old, &new).unwrap();
+        assert!(!diff.is_empty());
+        assert_eq!(diff.added.get("IDS_D").unwrap(), "Halloween");
+        assert_eq!(diff.removed.get("IDS_C").unwrap(), "Event");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].key, "IDS_B");
+        assert_eq!(diff.changed[0].old, "Yamato");
+        assert_eq!(diff.changed[0].new, "Musashi");
+        assert_eq!(
+            diff.to_text(),
+            "1 added, 1 removed, 1 changed\n+ IDS_D: Halloween\n- IDS_C: Event\n~ IDS_B: Yamato -> Musashi\n"
+        );
+
+        assert!(LangDiff::compare(&old, &old).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+// Merge the decoded global.mo of several languages into one table keyed by the string id
+
+use std::{collections::BTreeMap, fs::File, io::Write, path::Path};
+
+use log::info;
+
+use crate::types::UnpackResult;
+use crate::unpack::game_unpack::GameUnpacker;
+use crate::unpack::lang_unpack::LangUnpacker;
+use crate::utils::game::GameLanguages;
+
+/// {"IDS_PAAA001": {"en": "...", "ja": "..."}}
+pub type MergedStrings = BTreeMap<String, BTreeMap<String, String>>;
+
+pub struct LangMerger {
+    languages: Option<Vec<GameLanguages>>,
+    prefixes: Vec<String>,
+    merged: Vec<GameLanguages>,
+    strings: MergedStrings,
+}
+
+impl Default for LangMerger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LangMerger {
+    pub fn new() -> Self {
+        Self {
+            languages: None,
+            prefixes: Vec::new(),
+            merged: Vec::new(),
+            strings: BTreeMap::new(),
+        }
+    }
+
+    /// Only merge the given languages, every installed language is merged by default
+    pub fn languages(&mut self, languages: &[GameLanguages]) -> &mut Self {
+        self.languages = Some(languages.to_vec());
+        self
+    }
+
+    /// Only keep keys starting with one of the prefixes, e.g. IDS_PASB
+    pub fn prefixes(&mut self, prefixes: &[&str]) -> &mut Self {
+        self.prefixes = prefixes.iter().map(|p| p.to_string()).collect();
+        self
+    }
+
+    /**
+     * Decode the global.mo of each language and add its strings to the table
+     * @param unpacker The build to read from, every installed language is read unless languages() was called
+     * @return An error if a selected language is not installed
+     */
+    pub fn merge(&mut self, unpacker: &GameUnpacker) -> UnpackResult<&mut Self> {
+        let languages = match &self.languages {
+            Some(languages) => languages.clone(),
+            None => unpacker.installed_languages()?.languages,
+        };
+        for lang in languages.iter() {
+            let lang_path = unpacker.get_lang_path(lang);
+            info!("Merging language: {}", lang);
+            let mut lang_unpacker = LangUnpacker::new(lang_path)?;
+            lang_unpacker.decode()?;
+            self.add(lang, &lang_unpacker)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Merge an already decoded language
+    pub fn add(
+        &mut self,
+        language: &GameLanguages,
+        unpacker: &LangUnpacker,
+    ) -> UnpackResult<&mut Self> {
+        if !unpacker.is_decoded() {
+            return Err(Box::from(format!(
+                "Language {} is not decoded yet, call decode() before merging",
+                language
+            )));
+        }
+
+        let lang_key = language.to_folder_string();
+        for (key, value) in unpacker.text_data() {
+            if !self.matches_prefix(key) {
+                continue;
+            }
+
+            self.strings
+                .entry(key.clone())
+                .or_default()
+                .insert(lang_key.clone(), value.clone());
+        }
+
+        if !self.merged.contains(language) {
+            self.merged.push(*language);
+        }
+        Ok(self)
+    }
+
+    fn matches_prefix(&self, key: &str) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|p| key.starts_with(p.as_str()))
+    }
+
+    /// Languages which have been merged so far
+    pub fn merged_languages(&self) -> &[GameLanguages] {
+        &self.merged
+    }
+
+    pub fn strings(&self) -> &MergedStrings {
+        &self.strings
+    }
+
+    pub fn get(&self, key: &str, language: &GameLanguages) -> Option<&String> {
+        self.strings.get(key)?.get(&language.to_folder_string())
+    }
+
+    pub fn write_to_file(&self, file_name: &str, dest: &str) -> UnpackResult<()> {
+        let file_path = Path::new(&dest).join(file_name);
+        let mut file = File::create(file_path)?;
+        let json = serde_json::to_string(&self.strings)?;
+        file.write_all(json.as_bytes())?;
+        info!(
+            "Merged {} strings of {} languages into {}/{}",
+            self.strings.len(),
+            self.merged.len(),
+            dest,
+            file_name
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LangMerger;
+    use crate::unpack::{lang_unpack::LangUnpacker, test_utils::write_mo_file};
+    use crate::utils::game::GameLanguages;
+
+    #[test]
+    fn test_merge_languages() {
+        let en = write_mo_file(
+            "merge_en.mo",
+            &[("IDS_PASB017", "Montana"), ("IDS_OTHER", "Other")],
+        );
+        let ja = write_mo_file("merge_ja.mo", &[("IDS_PASB017", "モンタナ")]);
+
+        let mut en_unpacker = LangUnpacker::new(en).unwrap();
+        en_unpacker.decode().unwrap();
+        let mut ja_unpacker = LangUnpacker::new(ja).unwrap();
+        ja_unpacker.decode().unwrap();
+
+        let mut merger = LangMerger::new();
+        merger.prefixes(&["IDS_PA"]);
+        merger.add(&GameLanguages::EN, &en_unpacker).unwrap();
+        merger.add(&GameLanguages::JA, &ja_unpacker).unwrap();
+
+        assert_eq!(merger.strings().len(), 1);
+        assert_eq!(
+            merger.get("IDS_PASB017", &GameLanguages::EN).unwrap(),
+            "Montana"
+        );
+        assert_eq!(
+            merger.get("IDS_PASB017", &GameLanguages::JA).unwrap(),
+            "モンタナ"
+        );
+        assert!(merger.get("IDS_OTHER", &GameLanguages::EN).is_none());
+        assert_eq!(
+            merger.merged_languages(),
+            &[GameLanguages::EN, GameLanguages::JA]
+        );
+    }
+
+    #[test]
+    fn test_merge_requires_decode() {
+        let en = write_mo_file("merge_undecoded.mo", &[("IDS_A", "A")]);
+        let en_unpacker = LangUnpacker::new(en).unwrap();
+        assert!(LangMerger::new()
+            .add(&GameLanguages::EN, &en_unpacker)
+            .is_err());
+    }
+}
@@ -0,0 +1,246 @@
+// Translation coverage and consistency checks of all languages against a reference language
+
+use std::{collections::BTreeMap, fs::File, io::Write, path::Path};
+
+use log::info;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::types::UnpackResult;
+use crate::unpack::game_unpack::GameUnpacker;
+use crate::unpack::lang_merge::LangMerger;
+use crate::utils::game::GameLanguages;
+
+#[derive(Debug, Serialize)]
+pub struct PlaceholderMismatch {
+    pub key: String,
+    pub expected: Vec<String>,
+    pub found: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LanguageCoverage {
+    pub language: GameLanguages,
+    pub total: usize,
+    /// keys of the reference language which don't exist in this language
+    pub missing: Vec<String>,
+    /// keys whose value is still the msgid
+    pub untranslated: Vec<String>,
+    /// values whose placeholders or tags are different from the reference
+    pub mismatched: Vec<PlaceholderMismatch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LangReport {
+    pub reference: GameLanguages,
+    pub reference_total: usize,
+    pub languages: Vec<LanguageCoverage>,
+}
+
+impl LangReport {
+    /// Merge all installed languages of the game and check them against EN
+    pub fn generate(unpacker: &GameUnpacker) -> UnpackResult<Self> {
+        let mut merger = LangMerger::new();
+        merger.merge(unpacker)?;
+        Self::from_merged(&merger, &GameLanguages::EN)
+    }
+
+    /**
+     * Build the report from already merged languages
+     * @param merger The merged languages, it must contain the reference language
+     * @param reference The language every other language is compared with
+     */
+    pub fn from_merged(merger: &LangMerger, reference: &GameLanguages) -> UnpackResult<Self> {
+        if !merger.merged_languages().contains(reference) {
+            return Err(Box::from(format!(
+                "Reference language {} has not been merged",
+                reference
+            )));
+        }
+
+        let placeholder = placeholder_regex();
+        let reference_key = reference.to_folder_string();
+        let mut reference_total = 0;
+        let mut coverage: BTreeMap<GameLanguages, LanguageCoverage> = BTreeMap::new();
+        for lang in merger.merged_languages() {
+            coverage.insert(
+                *lang,
+                LanguageCoverage {
+                    language: *lang,
+                    total: 0,
+                    missing: Vec::new(),
+                    untranslated: Vec::new(),
+                    mismatched: Vec::new(),
+                },
+            );
+        }
+
+        for (key, values) in merger.strings() {
+            // the empty msgid holds the MO metadata
+            if key.is_empty() {
+                continue;
+            }
+
+            let expected = values.get(&reference_key);
+            if expected.is_some() {
+                reference_total += 1;
+            }
+            let expected = expected.map(|value| placeholders(&placeholder, value));
+
+            for (lang, report) in coverage.iter_mut() {
+                let value = match values.get(&lang.to_folder_string()) {
+                    Some(value) => value,
+                    None => {
+                        if expected.is_some() {
+                            report.missing.push(key.clone());
+                        }
+                        continue;
+                    }
+                };
+
+                report.total += 1;
+                if value == key {
+                    report.untranslated.push(key.clone());
+                }
+
+                if lang == reference {
+                    continue;
+                }
+
+                if let Some(expected) = &expected {
+                    let found = placeholders(&placeholder, value);
+                    if &found != expected {
+                        report.mismatched.push(PlaceholderMismatch {
+                            key: key.clone(),
+                            expected: expected.clone(),
+                            found,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            reference: *reference,
+            reference_total,
+            languages: coverage.into_values().collect(),
+        })
+    }
+
+    pub fn info(&self) -> &Self {
+        info!(
+            "Reference {} has {} strings",
+            self.reference, self.reference_total
+        );
+        for lang in &self.languages {
+            info!(
+                "{}: {} strings, {} missing, {} untranslated, {} mismatched",
+                lang.language,
+                lang.total,
+                lang.missing.len(),
+                lang.untranslated.len(),
+                lang.mismatched.len()
+            );
+        }
+
+        self
+    }
+
+    pub fn write_to_file(&self, file_name: &str, dest: &str) -> UnpackResult<()> {
+        let file_path = Path::new(&dest).join(file_name);
+        let mut file = File::create(file_path)?;
+        let json = serde_json::to_string_pretty(&self)?;
+        file.write_all(json.as_bytes())?;
+        info!("Language report written to {}/{}", dest, file_name);
+        Ok(())
+    }
+}
+
+/// %(name)s, %s, {0}, {name} and HTML-like tags such as <b> or </font>
+fn placeholder_regex() -> Regex {
+    Regex::new(r"%\([^)]*\)[a-zA-Z]|%[a-zA-Z]|\{[^{}\s]*\}|</?[a-zA-Z][a-zA-Z0-9]*[^<>]*>")
+        .expect("Invalid placeholder regex")
+}
+
+/// Get the sorted placeholders of a string, tags are reduced to their names
+fn placeholders(regex: &Regex, value: &str) -> Vec<String> {
+    let mut found: Vec<String> = regex
+        .find_iter(value)
+        .map(|m| {
+            let text = m.as_str();
+            if let Some(body) = text.strip_prefix('<') {
+                // attributes such as colors may differ between languages
+                let closing = if body.starts_with('/') { "/" } else { "" };
+                let name: String = body
+                    .trim_start_matches('/')
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric())
+                    .collect();
+                return format!("<{}{}>", closing, name.to_lowercase());
+            }
+            text.to_string()
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{placeholder_regex, placeholders, LangReport};
+    use crate::unpack::{
+        lang_merge::LangMerger, lang_unpack::LangUnpacker, test_utils::write_mo_file,
+    };
+    use crate::utils::game::GameLanguages;
+
+    #[test]
+    fn test_placeholders() {
+        let regex = placeholder_regex();
+        assert_eq!(
+            placeholders(&regex, "Deal %(damage)s to <b>{0}</b> 100%"),
+            vec!["%(damage)s", "</b>", "<b>", "{0}"]
+        );
+        assert_eq!(
+            placeholders(&regex, r#"<font color="red">{name}</font>"#),
+            vec!["</font>", "<font>", "{name}"]
+        );
+    }
+
+    #[test]
+    fn test_lang_report() {
+        let en = write_mo_file(
+            "report_en.mo",
+            &[
+                ("IDS_A", "Range %(dist)s km"),
+                ("IDS_B", "Battle"),
+                ("IDS_C", "Ship"),
+            ],
+        );
+        let de = write_mo_file(
+            "report_de.mo",
+            &[("IDS_A", "Reichweite km"), ("IDS_B", "IDS_B")],
+        );
+
+        let mut merger = LangMerger::new();
+        for (lang, path) in [(GameLanguages::EN, en), (GameLanguages::DE, de)] {
+            let mut unpacker = LangUnpacker::new(path).unwrap();
+            unpacker.decode().unwrap();
+            merger.add(&lang, &unpacker).unwrap();
+        }
+
+        let report = LangReport::from_merged(&merger, &GameLanguages::EN).unwrap();
+        assert_eq!(report.reference_total, 3);
+        let de = report
+            .languages
+            .iter()
+            .find(|lang| lang.language == GameLanguages::DE)
+            .unwrap();
+        assert_eq!(de.total, 2);
+        assert_eq!(de.missing, vec!["IDS_C"]);
+        assert_eq!(de.untranslated, vec!["IDS_B"]);
+        assert_eq!(de.mismatched.len(), 1);
+        assert_eq!(de.mismatched[0].key, "IDS_A");
+
+        assert!(LangReport::from_merged(&merger, &GameLanguages::JA).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+// Find string ids by their translated text
+
+use std::str::FromStr;
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use crate::types::UnpackResult;
+use crate::unpack::lang_merge::LangMerger;
+use crate::unpack::lang_unpack::LangUnpacker;
+use crate::utils::game::GameLanguages;
+
+/// How well a value matches the query, the best rank comes first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchRank {
+    Exact,
+    Prefix,
+    Partial,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextMatch {
+    pub key: String,
+    pub language: GameLanguages,
+    pub value: String,
+    pub rank: MatchRank,
+}
+
+pub struct TextSearch {
+    regex: Regex,
+    /// The query anchored at the start of the value
+    prefix: Regex,
+    /// The query covering the whole value
+    exact: Regex,
+}
+
+impl TextSearch {
+    /// Case insensitive substring search
+    pub fn substring(query: &str) -> UnpackResult<Self> {
+        Self::build(&regex::escape(query), true)
+    }
+
+    /// Regex search on the translated value, it is case sensitive unless (?i) is used
+    pub fn regex(query: &str) -> UnpackResult<Self> {
+        Self::build(query, false)
+    }
+
+    fn build(pattern: &str, case_insensitive: bool) -> UnpackResult<Self> {
+        let build = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(case_insensitive)
+                .build()
+        };
+        Ok(Self {
+            regex: build(pattern)?,
+            prefix: build(&format!("^(?:{})", pattern))?,
+            exact: build(&format!("^(?:{})$", pattern))?,
+        })
+    }
+
+    fn rank(&self, value: &str) -> Option<MatchRank> {
+        // the leftmost match may be shorter than another one, e.g. a|abc on abc
+        if self.exact.is_match(value) {
+            return Some(MatchRank::Exact);
+        }
+        if self.prefix.is_match(value) {
+            return Some(MatchRank::Prefix);
+        }
+        if self.regex.is_match(value) {
+            return Some(MatchRank::Partial);
+        }
+        None
+    }
+
+    /**
+     * Search one decoded language
+     * @param unpacker The decoded MO file
+     * @param language The language of the MO file
+     * @return The matches ranked with exact matches first
+     */
+    pub fn search(
+        &self,
+        unpacker: &LangUnpacker,
+        language: &GameLanguages,
+    ) -> UnpackResult<Vec<TextMatch>> {
+        if !unpacker.is_decoded() {
+            return Err(Box::from(
+                "Text data is not decoded yet, call decode() before searching",
+            ));
+        }
+
+        let mut matches = Vec::new();
+        for (key, value) in unpacker.text_data() {
+            self.push_match(&mut matches, key, language, value);
+        }
+        Self::sort(&mut matches);
+        Ok(matches)
+    }
+
+    /// Search every language of the merged table
+    pub fn search_merged(&self, merger: &LangMerger) -> Vec<TextMatch> {
+        let mut matches = Vec::new();
+        for (key, values) in merger.strings() {
+            for (lang, value) in values {
+                let language = match GameLanguages::from_str(lang) {
+                    Ok(language) => language,
+                    Err(_) => continue,
+                };
+                self.push_match(&mut matches, key, &language, value);
+            }
+        }
+        Self::sort(&mut matches);
+        matches
+    }
+
+    fn push_match(
+        &self,
+        matches: &mut Vec<TextMatch>,
+        key: &str,
+        language: &GameLanguages,
+        value: &str,
+    ) {
+        // the empty msgid holds the MO metadata
+        if key.is_empty() {
+            return;
+        }
+
+        if let Some(rank) = self.rank(value) {
+            matches.push(TextMatch {
+                key: key.to_string(),
+                language: *language,
+                value: value.to_string(),
+                rank,
+            });
+        }
+    }
+
+    fn sort(matches: &mut [TextMatch]) {
+        // shorter values are closer to the query
+        matches.sort_by(|a, b| {
+            a.rank
+                .cmp(&b.rank)
+                .then(a.value.len().cmp(&b.value.len()))
+                .then(a.key.cmp(&b.key))
+                .then(a.language.cmp(&b.language))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MatchRank, TextSearch};
+    use crate::unpack::{
+        lang_merge::LangMerger, lang_unpack::LangUnpacker, test_utils::write_mo_file,
+    };
+    use crate::utils::game::GameLanguages;
+
+    fn decode(name: &str, strings: &[(&str, &str)]) -> LangUnpacker {
+        let mut unpacker = LangUnpacker::new(write_mo_file(name, strings)).unwrap();
+        unpacker.decode().unwrap();
+        unpacker
+    }
+
+    #[test]
+    fn test_substring_search() {
+        let en = decode(
+            "search_en.mo",
+            &[
+                ("IDS_PASB017", "Montana"),
+                ("IDS_PASB017_DESCR", "The Montana is a battleship"),
+                ("IDS_PASB018", "Montana Prime"),
+                ("IDS_PJSB018", "Yamato"),
+            ],
+        );
+
+        let matches = TextSearch::substring("montana")
+            .unwrap()
+            .search(&en, &GameLanguages::EN)
+            .unwrap();
+        let keys: Vec<&str> = matches.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec!["IDS_PASB017", "IDS_PASB018", "IDS_PASB017_DESCR"]
+        );
+        assert_eq!(matches[0].rank, MatchRank::Exact);
+        assert_eq!(matches[1].rank, MatchRank::Prefix);
+        assert_eq!(matches[2].rank, MatchRank::Partial);
+    }
+
+    #[test]
+    fn test_regex_search_merged() {
+        let en = decode("search_merged_en.mo", &[("IDS_PJSB018", "Yamato")]);
+        let ja = decode("search_merged_ja.mo", &[("IDS_PJSB018", "大和")]);
+        let mut merger = LangMerger::new();
+        merger.add(&GameLanguages::EN, &en).unwrap();
+        merger.add(&GameLanguages::JA, &ja).unwrap();
+
+        let matches = TextSearch::regex("^(Yamato|大和)$")
+            .unwrap()
+            .search_merged(&merger);
+        assert_eq!(matches.len(), 2);
+        assert!(matches.iter().all(|m| m.key == "IDS_PJSB018"));
+        assert!(matches.iter().all(|m| m.rank == MatchRank::Exact));
+
+        assert!(TextSearch::regex("(").is_err());
+    }
+
+    #[test]
+    fn test_rank_alternation() {
+        let en = decode(
+            "search_rank_en.mo",
+            &[("IDS_A", "abc"), ("IDS_B", "x abc abd"), ("IDS_C", "abd x")],
+        );
+        let matches = TextSearch::regex("a|abc|abd x")
+            .unwrap()
+            .search(&en, &GameLanguages::EN)
+            .unwrap();
+        let ranks: Vec<(&str, MatchRank)> =
+            matches.iter().map(|m| (m.key.as_str(), m.rank)).collect();
+        assert_eq!(
+            ranks,
+            vec![
+                ("IDS_A", MatchRank::Exact),
+                ("IDS_C", MatchRank::Exact),
+                ("IDS_B", MatchRank::Partial)
+            ]
+        );
+    }
+}
@@ -7,7 +7,7 @@ use std::{
     path::Path,
 };
 
-use log::{debug, info, warn, error};
+use log::{debug, error, info, warn};
 use serde::Deserialize;
 
 use crate::types::UnpackResult;
@@ -114,6 +114,15 @@ impl LangUnpacker {
         Ok(self)
     }
 
+    /// All decoded strings keyed by their msgid, empty until decode() is called
+    pub fn text_data(&self) -> &HashMap<String, String> {
+        &self.text_data
+    }
+
+    pub fn is_decoded(&self) -> bool {
+        self.decoded
+    }
+
     pub fn write_to_file(&self, file_name: &str, dest: &str) -> UnpackResult<()> {
         if !self.decoded {
             return Err(Box::from(
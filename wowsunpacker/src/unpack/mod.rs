@@ -1,3 +1,190 @@
+pub mod game_event;
+pub mod game_export;
+pub mod game_recipe;
 pub mod game_unpack;
+pub mod lang_diff;
+pub mod lang_merge;
+pub mod lang_report;
+pub mod lang_search;
 pub mod lang_unpack;
+pub mod params_decode;
+pub mod params_diff;
+pub mod params_encode;
+pub mod params_format;
+pub mod params_localize;
+pub mod params_model;
+pub mod params_resolve;
+pub mod params_split;
 pub mod params_unpack;
+pub mod params_value;
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::unpack::game_unpack::GameUnpacker;
+
+    /// The build of the games written by unpacker_with()
+    pub const TEST_BUILD: u32 = 1234;
+
+    /// A new empty directory under the system temp folder, unique for every call
+    pub fn temp_dir(name: &str) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join("wowsunpacker_test").join(format!(
+            "{}_{}_{}",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write a minimal little endian MO file into a temp folder and return its path
+    pub fn write_mo_file(name: &str, strings: &[(&str, &str)]) -> String {
+        let count = strings.len() as u32;
+        let originals = 28;
+        let translations = originals + count * 8;
+        let mut offset = translations + count * 8;
+
+        let mut tables = Vec::new();
+        let mut data = Vec::new();
+        // write all keys first, then all values
+        for pick_key in [true, false] {
+            for (key, value) in strings {
+                let text = if pick_key { key } else { value };
+                tables.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                tables.extend_from_slice(&offset.to_le_bytes());
+                data.extend_from_slice(text.as_bytes());
+                data.push(0);
+                offset += text.len() as u32 + 1;
+            }
+        }
+
+        let mut mo = Vec::new();
+        for value in [0x950412de, 0, count, originals, translations, 0, 0] {
+            mo.extend_from_slice(&value.to_le_bytes());
+        }
+        mo.extend(tables);
+        mo.extend(data);
+
+        let path = temp_dir("mo").join(name);
+        std::fs::write(&path, mo).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    /// Build one idx file and its pkg file, the files are deflated unless that doesn't shrink them
+    fn write_package(game_path: &Path, build: u32, pkg_name: &str, files: &[(&str, &[u8])]) {
+        use flate2::{write::DeflateEncoder, Compression};
+        use std::io::Write;
+
+        // every directory and file is a node, ids start at 1 as 0 is the parent of the root
+        let mut nodes: Vec<(String, u64, u64)> = Vec::new();
+        let mut leaves = Vec::new();
+        for (path, _) in files {
+            let mut parent = 0;
+            let mut current = String::new();
+            for part in path.split('/') {
+                current = if current.is_empty() {
+                    part.to_string()
+                } else {
+                    format!("{}/{}", current, part)
+                };
+                parent = match nodes.iter().find(|(node_path, _, _)| *node_path == current) {
+                    Some((_, id, _)) => *id,
+                    None => {
+                        let id = nodes.len() as u64 + 1;
+                        nodes.push((current.clone(), id, parent));
+                        id
+                    }
+                };
+            }
+            leaves.push(parent);
+        }
+
+        let mut pkg = Vec::new();
+        let mut records = Vec::new();
+        for ((_, data), id) in files.iter().zip(&leaves) {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).unwrap();
+            let compressed = encoder.finish().unwrap();
+            let stored = if compressed.len() < data.len() {
+                compressed
+            } else {
+                data.to_vec()
+            };
+
+            let mut record = Vec::new();
+            record.extend_from_slice(&id.to_le_bytes());
+            record.extend_from_slice(&0u64.to_le_bytes());
+            record.extend_from_slice(&(pkg.len() as i64).to_le_bytes());
+            record.extend_from_slice(&0u64.to_le_bytes());
+            record.extend_from_slice(&(stored.len() as i32).to_le_bytes());
+            record.extend_from_slice(&0u32.to_le_bytes());
+            record.extend_from_slice(&(data.len() as i64).to_le_bytes());
+            records.push(record);
+            pkg.extend(stored);
+        }
+
+        // header, nodes, names, file records and the trailer with the pkg name
+        let names_offset = 56 + nodes.len() * 32;
+        let mut names = Vec::new();
+        let mut node_data = Vec::new();
+        for (i, (path, id, parent)) in nodes.iter().enumerate() {
+            let name = path.rsplit('/').next().unwrap();
+            let pointer = names_offset + names.len() - (56 + i * 32);
+            node_data.extend_from_slice(&0u64.to_le_bytes());
+            node_data.extend_from_slice(&(pointer as u64).to_le_bytes());
+            node_data.extend_from_slice(&id.to_le_bytes());
+            node_data.extend_from_slice(&parent.to_le_bytes());
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+        let records_offset = names_offset + names.len();
+        let trailer_offset = records_offset + records.len() * 48;
+
+        let mut idx = b"ISFP".to_vec();
+        idx.extend_from_slice(&[0; 12]);
+        idx.extend_from_slice(&(nodes.len() as i32).to_le_bytes());
+        idx.extend_from_slice(&(files.len() as i32).to_le_bytes());
+        idx.extend_from_slice(&[0; 16]);
+        idx.extend_from_slice(&(records_offset as i64 - 0x10).to_le_bytes());
+        idx.extend_from_slice(&(trailer_offset as i64 - 0x10).to_le_bytes());
+        idx.extend(node_data);
+        idx.extend(names);
+        idx.extend(records.concat());
+        idx.extend_from_slice(&[0; 24]);
+        idx.extend_from_slice(pkg_name.as_bytes());
+        idx.push(0);
+
+        let idx_dir = game_path.join("bin").join(build.to_string()).join("idx");
+        let pkg_dir = game_path.join("res_packages");
+        std::fs::create_dir_all(&idx_dir).unwrap();
+        std::fs::create_dir_all(&pkg_dir).unwrap();
+        let idx_name = pkg_name.replace(".pkg", ".idx");
+        std::fs::write(idx_dir.join(idx_name), idx).unwrap();
+        std::fs::write(pkg_dir.join(pkg_name), pkg).unwrap();
+    }
+
+    /// The files of one pkg with their content
+    pub type PackageFiles<'a> = &'a [(&'a str, &'a [u8])];
+
+    /**
+     * Write a minimal game with bin/TEST_BUILD/idx and res_packages into a temp folder and open it
+     * @param packages The pkg names with the files they contain
+     * @return The unpacker with its directory tree built
+     */
+    pub fn unpacker_with(packages: &[(&str, PackageFiles)]) -> GameUnpacker {
+        let game_path = temp_dir("game");
+        for (pkg_name, files) in packages {
+            write_package(&game_path, TEST_BUILD, pkg_name, files);
+        }
+        let mut unpacker = GameUnpacker::auto(game_path.to_str().unwrap()).unwrap();
+        unpacker.build_directory_tree().unwrap();
+        unpacker
+    }
+}
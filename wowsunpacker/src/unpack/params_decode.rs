@@ -0,0 +1,384 @@
+// Native GameParams.data decoder, the same steps as paramsunpack/wowsunpack/main.cs
+// GameParams.data is a zlib compressed pickle with all bytes reversed
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, Write},
+    path::Path,
+};
+
+use flate2::read::ZlibDecoder;
+use log::info;
+use serde_json::{Map, Number, Value as JsonValue};
+
+use crate::types::UnpackResult;
+use crate::unpack::params_format::ParamsFormat;
+use crate::unpack::params_value::PyValue;
+use crate::utils::functions::ReverseReader;
+use crate::utils::pickle::{self, ObjectKind, Unpickler, Value};
+
+// cyclic references are not supported
+const MAX_DEPTH: usize = 512;
+
+pub struct ParamsDecoder;
+
+impl ParamsDecoder {
+    /**
+     * Decode the raw content of GameParams.data
+     * @param data The reversed and compressed data
+     * @return The params tree, objects are replaced by their state
+     */
+    pub fn decode(data: &[u8]) -> UnpackResult<JsonValue> {
+        let pickled = Self::inflate(data)?;
+        info!("Inflated GameParams to {} bytes", pickled.len());
+        let unpickled = pickle::loads(&pickled)?;
+        info!("Unpickled GameParams");
+
+        // the params are the first item of the tuple
+        let params = match &unpickled {
+            Value::Tuple(items) => items.first().cloned(),
+            Value::List(items) => items.borrow().first().cloned(),
+            _ => None,
+        }
+        .ok_or("GameParams is not a tuple with the params")?;
+        to_json(&params, 0)
+    }
+
+    pub fn decode_file(path: &str) -> UnpackResult<JsonValue> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        Self::decode(&data)
+    }
+
+    /**
+     * Decode the raw content of GameParams.data without losing the Python types
+     * @param data The reversed and compressed data
+     * @return The whole unpickled tuple, the params are PyValue::params()
+     */
+    pub fn decode_lossless(data: &[u8]) -> UnpackResult<PyValue> {
+        let pickled = Self::inflate(data)?;
+        let unpickled = pickle::loads(&pickled)?;
+        PyValue::from_pickle(&unpickled, 0)
+    }
+
+    /**
+     * Decode GameParams.data without loading it into memory, entities are emitted one at a time
+     * The file is read from the end in chunks and inflated while the pickle is running
+     * @param reader The reversed and compressed data, e.g. a File
+     * @param callback Called with the name and the params of every entity
+     * @return The number of entities
+     */
+    pub fn decode_streaming<R: Read + Seek>(
+        reader: R,
+        mut callback: impl FnMut(String, JsonValue) -> UnpackResult<()>,
+    ) -> UnpackResult<usize> {
+        let inflated = BufReader::new(ZlibDecoder::new(ReverseReader::new(reader)?));
+        let mut count = 0;
+        Unpickler::new(inflated)
+            .with_entity_callback(|key, value| {
+                count += 1;
+                callback(key_to_string(&key)?, to_json(&value, 0)?)
+            })
+            .load()?;
+        info!("Streamed {} GameParams entities", count);
+        Ok(count)
+    }
+
+    pub fn decode_file_streaming(
+        path: &str,
+        callback: impl FnMut(String, JsonValue) -> UnpackResult<()>,
+    ) -> UnpackResult<usize> {
+        Self::decode_streaming(File::open(path)?, callback)
+    }
+
+    /**
+     * Stream GameParams.data into a json object keyed by entity name, one entity per line
+     * The output loads like the json written by write_to_file
+     * @param reader The reversed and compressed data, e.g. a File
+     * @param writer The json output
+     * @return The number of entities
+     */
+    pub fn stream_to_writer<R: Read + Seek, W: Write>(reader: R, writer: W) -> UnpackResult<usize> {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(b"{")?;
+        let mut first = true;
+        let count = Self::decode_streaming(reader, |name, data| {
+            writer.write_all(if first { b"\n" } else { b",\n" })?;
+            first = false;
+            serde_json::to_writer(&mut writer, &name)?;
+            writer.write_all(b": ")?;
+            serde_json::to_writer(&mut writer, &data)?;
+            Ok(())
+        })?;
+        writer.write_all(b"\n}\n")?;
+        writer.flush()?;
+        Ok(count)
+    }
+
+    /// Reverse the data and decompress the zlib stream into the pickle data
+    pub fn inflate(data: &[u8]) -> UnpackResult<Vec<u8>> {
+        let reversed: Vec<u8> = data.iter().rev().copied().collect();
+        let mut pickled = Vec::new();
+        ZlibDecoder::new(reversed.as_slice()).read_to_end(&mut pickled)?;
+        Ok(pickled)
+    }
+
+    pub fn write_to_file(params: &JsonValue, file_path: &str, compact: bool) -> UnpackResult<()> {
+        ParamsFormat::Json.write(params, file_path, compact)
+    }
+
+    /// Write the lossless value as tagged json
+    pub fn write_lossless_to_file(
+        value: &PyValue,
+        file_path: &str,
+        compact: bool,
+    ) -> UnpackResult<()> {
+        if let Some(parent) = Path::new(file_path).parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        if compact {
+            serde_json::to_writer(&mut writer, value)?;
+        } else {
+            serde_json::to_writer_pretty(&mut writer, value)?;
+        }
+        writer.flush()?;
+        info!("Lossless GameParams written to {}", file_path);
+        Ok(())
+    }
+}
+
+fn bytes_to_string(data: &[u8]) -> String {
+    // Python 2 str is not always utf8, fall back to latin1 like pickle.loads(encoding='latin1')
+    match std::str::from_utf8(data) {
+        Ok(text) => text.to_string(),
+        Err(_) => data.iter().map(|c| *c as char).collect(),
+    }
+}
+
+/// Format a float like Python repr(), e.g. 1.0 and 1e+16, so keys match str() of the reference dump
+fn float_repr(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+
+    // Debug is the shortest repr that round-trips and uses exponents in the same ranges as Python
+    let repr = format!("{:?}", value);
+    match repr.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let (sign, digits) = match exponent.strip_prefix('-') {
+                Some(digits) => ('-', digits),
+                None => ('+', exponent),
+            };
+            format!("{}e{}{:0>2}", mantissa, sign, digits)
+        }
+        None => repr,
+    }
+}
+
+fn key_to_string(key: &Value) -> UnpackResult<String> {
+    match key {
+        Value::String(text) => Ok(text.to_string()),
+        Value::Bytes(data) => Ok(bytes_to_string(data)),
+        Value::None => Ok("None".to_string()),
+        Value::Bool(value) => Ok(if *value { "True" } else { "False" }.to_string()),
+        Value::Int(value) => Ok(value.to_string()),
+        Value::Long(value) => Ok(value.to_string()),
+        Value::Float(value) => Ok(float_repr(*value)),
+        key => Ok(serde_json::to_string(&to_json(key, 0)?)?),
+    }
+}
+
+fn items_to_json(items: &[Value], depth: usize) -> UnpackResult<JsonValue> {
+    let mut array = Vec::with_capacity(items.len());
+    for item in items {
+        array.push(to_json(item, depth + 1)?);
+    }
+    Ok(JsonValue::Array(array))
+}
+
+fn insert_items(
+    map: &mut Map<String, JsonValue>,
+    items: &[(Value, Value)],
+    depth: usize,
+) -> UnpackResult<()> {
+    for (key, value) in items {
+        map.insert(key_to_string(key)?, to_json(value, depth + 1)?);
+    }
+    Ok(())
+}
+
+/// Convert the unpickled value to json, objects become the dict of their state
+fn to_json(value: &Value, depth: usize) -> UnpackResult<JsonValue> {
+    if depth > MAX_DEPTH {
+        return Err(Box::from("GameParams is nested too deeply"));
+    }
+
+    let json = match value {
+        Value::None => JsonValue::Null,
+        Value::Bool(value) => JsonValue::Bool(*value),
+        Value::Int(value) => JsonValue::from(*value),
+        Value::Long(value) => match (i64::try_from(*value), u64::try_from(*value)) {
+            (Ok(value), _) => JsonValue::from(value),
+            (_, Ok(value)) => JsonValue::from(value),
+            _ => JsonValue::String(value.to_string()),
+        },
+        // json has no NaN and infinity
+        Value::Float(value) => Number::from_f64(*value)
+            .map(JsonValue::Number)
+            .unwrap_or(JsonValue::Null),
+        Value::Bytes(data) => JsonValue::String(bytes_to_string(data)),
+        Value::String(text) => JsonValue::String(text.to_string()),
+        Value::Tuple(items) => items_to_json(items, depth)?,
+        Value::FrozenSet(items) => items_to_json(items, depth)?,
+        Value::List(items) => items_to_json(&items.borrow(), depth)?,
+        Value::Set(items) => items_to_json(&items.borrow(), depth)?,
+        Value::Dict(items) => {
+            let mut map = Map::new();
+            insert_items(&mut map, &items.borrow(), depth)?;
+            JsonValue::Object(map)
+        }
+        Value::Global(global) => JsonValue::String(format!("{}.{}", global.0, global.1)),
+        Value::Object(object) => {
+            let object = object.borrow();
+            if object.dict_items.is_empty() && !object.list_items.is_empty() {
+                return items_to_json(&object.list_items, depth);
+            }
+
+            // objects which are not DataTables keep their constructor arguments
+            let reconstructed = object.kind == ObjectKind::Reduce
+                && matches!(
+                    object.callable.as_global(),
+                    Some(("copy_reg" | "copyreg", "_reconstructor"))
+                );
+            if !reconstructed && object.state.is_none() && object.dict_items.is_empty() {
+                if let Value::Tuple(args) = &object.args {
+                    if !args.is_empty() {
+                        return items_to_json(args, depth);
+                    }
+                }
+            }
+
+            let mut map = Map::new();
+            insert_items(&mut map, &object.dict_items, depth)?;
+            match &object.state {
+                Some(Value::Dict(state)) => insert_items(&mut map, &state.borrow(), depth)?,
+                // (state, slotstate)
+                Some(Value::Tuple(state)) => {
+                    for part in state.iter() {
+                        if let Value::Dict(part) = part {
+                            insert_items(&mut map, &part.borrow(), depth)?;
+                        }
+                    }
+                }
+                Some(Value::None) | None => {}
+                Some(state) => return to_json(state, depth + 1),
+            }
+            JsonValue::Object(map)
+        }
+    };
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{float_repr, ParamsDecoder};
+    use crate::unpack::params_value::PyValue;
+    use flate2::{write::ZlibEncoder, Compression};
+    use serde_json::{json, Value as JsonValue};
+    use std::io::{Cursor, Write};
+
+    /// A protocol 1 pickle of (DataTable with the state below,) like the game uses
+    const PICKLE: &[u8] = b"(ccopy_reg\n_reconstructor\nq\x00(cGameParams\nGPData\nq\x01c__builtin__\nobject\nq\x02Ntq\x03Rq\x04}q\x05(U\x04nameq\x06U\x14PASB017_Montana_1945q\x07U\x02idq\x08J\x94\xa1\x1c\x10U\x08typeinfoq\t}q\n(U\x07speciesq\x0bU\nBattleshipq\x0cU\x06nationq\rU\x03USAq\x0euU\x05rangeq\x0fG@2\x80\x00\x00\x00\x00\x00U\x06pointsq\x10]q\x11((K\x01K\x02t]q\x12(h\x0fNeeubtq\x13.";
+
+    fn encode(pickled: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(pickled).unwrap();
+        let mut data = encoder.finish().unwrap();
+        data.reverse();
+        data
+    }
+
+    #[test]
+    fn test_decode_params() {
+        let params = ParamsDecoder::decode(&encode(PICKLE)).unwrap();
+        assert_eq!(
+            params,
+            json!({
+                "name": "PASB017_Montana_1945",
+                "id": 270311828,
+                "typeinfo": {"species": "Battleship", "nation": "USA"},
+                "range": 18.5,
+                "points": [[1, 2], ["range", null]],
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_lossless() {
+        let root = ParamsDecoder::decode_lossless(&encode(PICKLE)).unwrap();
+        let params = root.params().unwrap();
+        assert_eq!(params.get("id"), Some(&PyValue::Int(270311828)));
+        assert_eq!(
+            params.get("points"),
+            Some(&PyValue::List(vec![
+                PyValue::Tuple(vec![PyValue::Int(1), PyValue::Int(2)]),
+                PyValue::List(vec![PyValue::Bytes(b"range".to_vec()), PyValue::None]),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_decode_streaming() {
+        // ({'PASB017_Montana': <GPData>, 'PJSB018_Yamato': <GPData>},) which shares the keys in the memo
+        let pickled = b"(}q\x00(X\x0f\x00\x00\x00PASB017_Montanaq\x01ccopy_reg\n_reconstructor\nq\x02(cGameParams\nGPData\nq\x03c__builtin__\nobject\nq\x04Ntq\x05Rq\x06}q\x07(X\x04\x00\x00\x00nameq\x08X\x07\x00\x00\x00PASB017q\tX\x08\x00\x00\x00typeinfoq\nh\x02(h\x03h\x04Ntq\x0bRq\x0c}q\rX\x06\x00\x00\x00nationq\x0eX\x03\x00\x00\x00USAq\x0fsbX\x04\x00\x00\x00ammoq\x10]q\x11X\x07\x00\x00\x00PAPA001q\x12aubX\x0e\x00\x00\x00PJSB018_Yamatoq\x13h\x02(h\x03h\x04Ntq\x14Rq\x15}q\x16(h\x08X\x07\x00\x00\x00PJSB018q\x17h\nh\x02(h\x03h\x04Ntq\x18Rq\x19}q\x1ah\x0eX\x05\x00\x00\x00Japanq\x1bsbh\x10]q\x1ch\x12aubutq\x1d.";
+        let data = encode(pickled);
+
+        let mut entities = Vec::new();
+        let count = ParamsDecoder::decode_streaming(Cursor::new(&data), |name, params| {
+            entities.push((name, params));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(entities[0].0, "PASB017_Montana");
+        assert_eq!(
+            entities[1].1,
+            json!({"name": "PJSB018", "typeinfo": {"nation": "Japan"}, "ammo": ["PAPA001"]})
+        );
+
+        // the streamed json loads like the decoded params
+        let mut output = Vec::new();
+        ParamsDecoder::stream_to_writer(Cursor::new(&data), &mut output).unwrap();
+        let streamed: JsonValue = serde_json::from_slice(&output).unwrap();
+        assert_eq!(streamed, ParamsDecoder::decode(&data).unwrap());
+    }
+
+    #[test]
+    fn test_decode_invalid_params() {
+        assert!(ParamsDecoder::decode(b"not a game params file").is_err());
+        assert!(ParamsDecoder::decode(&encode(b"(K\x01")).is_err());
+    }
+
+    #[test]
+    fn test_float_repr() {
+        for (value, expected) in [
+            (1.0, "1.0"),
+            (0.1, "0.1"),
+            (-0.0, "-0.0"),
+            (1e16, "1e+16"),
+            (1e15, "1000000000000000.0"),
+            (1e-5, "1e-05"),
+            (1.5e300, "1.5e+300"),
+            (f64::NEG_INFINITY, "-inf"),
+        ] {
+            assert_eq!(float_repr(value), expected);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// Structural diff of two GameParams keyed by entity name and value path
+
+use std::{collections::BTreeMap, fmt::Write as _, fs::File, io::Write, path::Path};
+
+use log::info;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::types::UnpackResult;
+use crate::unpack::game_unpack::GameUnpacker;
+use crate::unpack::params_model::GameParams;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueChange {
+    /// e.g. A_Artillery.HP_AGM_1.maxDist or ammoList[0]
+    pub path: String,
+    /// None if the value has been added
+    pub old: Option<JsonValue>,
+    /// None if the value has been removed
+    pub new: Option<JsonValue>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ParamsDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: BTreeMap<String, Vec<ValueChange>>,
+}
+
+fn join_key(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Collect every leaf which is different between old and new
+fn diff_values(
+    path: &str,
+    old: Option<&JsonValue>,
+    new: Option<&JsonValue>,
+    changes: &mut Vec<ValueChange>,
+) {
+    match (old, new) {
+        (Some(JsonValue::Object(old)), Some(JsonValue::Object(new))) => {
+            for (key, old_value) in old {
+                diff_values(&join_key(path, key), Some(old_value), new.get(key), changes);
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    diff_values(&join_key(path, key), None, Some(new_value), changes);
+                }
+            }
+        }
+        (Some(JsonValue::Array(old)), Some(JsonValue::Array(new))) => {
+            for i in 0..old.len().max(new.len()) {
+                let item_path = format!("{}[{}]", path, i);
+                diff_values(&item_path, old.get(i), new.get(i), changes);
+            }
+        }
+        (old, new) => {
+            if old != new {
+                changes.push(ValueChange {
+                    path: path.to_string(),
+                    old: old.cloned(),
+                    new: new.cloned(),
+                });
+            }
+        }
+    }
+}
+
+fn display(value: &Option<JsonValue>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "(none)".to_string(),
+    }
+}
+
+impl ParamsDiff {
+    /**
+     * Compare two decoded GameParams
+     * @param old The params of the older build
+     * @param new The params of the newer build
+     */
+    pub fn compare(old: &GameParams, new: &GameParams) -> Self {
+        let mut diff = ParamsDiff::default();
+        for entity in old.entities() {
+            match new.get(entity.name) {
+                None => diff.removed.push(entity.name.to_string()),
+                Some(new_entity) => {
+                    let mut changes = Vec::new();
+                    diff_values("", Some(entity.data), Some(new_entity.data), &mut changes);
+                    if !changes.is_empty() {
+                        diff.changed.insert(entity.name.to_string(), changes);
+                    }
+                }
+            }
+        }
+
+        for entity in new.entities() {
+            if old.get(entity.name).is_none() {
+                diff.added.push(entity.name.to_string());
+            }
+        }
+
+        diff.added.sort();
+        diff.removed.sort();
+        info!(
+            "GameParams diff: {} added, {} removed, {} changed",
+            diff.added.len(),
+            diff.removed.len(),
+            diff.changed.len()
+        );
+        diff
+    }
+
+    /// Decode the GameParams of two builds, the directory trees have to be built
+    pub fn between(old: &GameUnpacker, new: &GameUnpacker) -> UnpackResult<Self> {
+        let old = GameParams::from_unpacker(old)?;
+        let new = GameParams::from_unpacker(new)?;
+        Ok(Self::compare(&old, &new))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// A readable report with one line per entity or changed value
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(
+            text,
+            "{} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        );
+        for name in &self.added {
+            let _ = writeln!(text, "+ {}", name);
+        }
+        for name in &self.removed {
+            let _ = writeln!(text, "- {}", name);
+        }
+        for (name, changes) in &self.changed {
+            let _ = writeln!(text, "~ {}", name);
+            for change in changes {
+                let _ = writeln!(
+                    text,
+                    "    {}: {} -> {}",
+                    change.path,
+                    display(&change.old),
+                    display(&change.new)
+                );
+            }
+        }
+        text
+    }
+
+    pub fn write_to_file(&self, file_name: &str, dest: &str) -> UnpackResult<()> {
+        let file_path = Path::new(&dest).join(file_name);
+        let mut file = File::create(file_path)?;
+        let json = serde_json::to_string_pretty(&self)?;
+        file.write_all(json.as_bytes())?;
+        info!("GameParams diff written to {}/{}", dest, file_name);
+        Ok(())
+    }
+
+    pub fn write_report(&self, file_name: &str, dest: &str) -> UnpackResult<()> {
+        let file_path = Path::new(&dest).join(file_name);
+        let mut file = File::create(file_path)?;
+        file.write_all(self.to_text().as_bytes())?;
+        info!("GameParams report written to {}/{}", dest, file_name);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParamsDiff, ValueChange};
+    use crate::unpack::params_model::GameParams;
+    use serde_json::json;
+
+    #[test]
+    fn test_params_diff() {
+        let old = GameParams::new(json!({
+            "PASB017_Montana_1945": {
+                "A_Artillery": {"HP_AGM_1": {"maxDist": 17.5}},
+                "ammoList": ["PAPA001", "PAPA002"],
+            },
+            "PASB001_Old_Ship": {},
+        }))
+        .unwrap();
+        let new = GameParams::new(json!({
+            "PASB017_Montana_1945": {
+                "A_Artillery": {"HP_AGM_1": {"maxDist": 18.2, "delim": 0.5}},
+                "ammoList": ["PAPA001"],
+            },
+            "PASB099_New_Ship": {},
+        }))
+        .unwrap();
+
+        let diff = ParamsDiff::compare(&old, &new);
+        assert_eq!(diff.added, vec!["PASB099_New_Ship"]);
+        assert_eq!(diff.removed, vec!["PASB001_Old_Ship"]);
+        let changes = diff.changed.get("PASB017_Montana_1945").unwrap();
+        assert_eq!(
+            changes,
+            &vec![
+                ValueChange {
+                    path: "A_Artillery.HP_AGM_1.maxDist".to_string(),
+                    old: Some(json!(17.5)),
+                    new: Some(json!(18.2)),
+                },
+                ValueChange {
+                    path: "A_Artillery.HP_AGM_1.delim".to_string(),
+                    old: None,
+                    new: Some(json!(0.5)),
+                },
+                ValueChange {
+                    path: "ammoList[1]".to_string(),
+                    old: Some(json!("PAPA002")),
+                    new: None,
+                },
+            ]
+        );
+
+        let text = diff.to_text();
+        assert!(text.contains("    A_Artillery.HP_AGM_1.maxDist: 17.5 -> 18.2"));
+        assert!(text.contains("+ PASB099_New_Ship"));
+        assert!(ParamsDiff::compare(&new, &new).is_empty());
+    }
+}
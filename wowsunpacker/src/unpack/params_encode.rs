@@ -0,0 +1,512 @@
+// GameParams.data encoder, the inverse of ParamsDecoder
+// The params are pickled with protocol 1 like the game, compressed with zlib and reversed
+
+use std::{collections::HashSet, fs::File, io::Write, path::Path};
+
+use flate2::{write::ZlibEncoder, Compression};
+use log::info;
+use serde_json::Value as JsonValue;
+
+use crate::types::UnpackResult;
+use crate::unpack::params_value::{PyObject, PyValue};
+use crate::utils::pickle::ObjectKind;
+
+// cyclic references are not supported
+const MAX_DEPTH: usize = 512;
+
+pub struct ParamsEncoder;
+
+impl ParamsEncoder {
+    /**
+     * Encode the whole unpickled tuple, e.g. from ParamsDecoder::decode_lossless
+     * @param root The root value of GameParams.data
+     * @return The reversed and compressed data
+     */
+    pub fn encode(root: &PyValue) -> UnpackResult<Vec<u8>> {
+        let pickled = Self::pickle(root)?;
+        info!("Pickled GameParams to {} bytes", pickled.len());
+        Self::deflate(&pickled)
+    }
+
+    /**
+     * Encode a params tree keyed by entity name, e.g. from ParamsDecoder::decode
+     * @param params The json params, every object becomes a GameParams.GPData
+     * @return The reversed and compressed data
+     */
+    pub fn encode_json(params: &JsonValue) -> UnpackResult<Vec<u8>> {
+        Self::encode(&Self::to_value(params)?)
+    }
+
+    /// Build the root tuple of the game from json, objects become copy_reg._reconstructor objects
+    pub fn to_value(params: &JsonValue) -> UnpackResult<PyValue> {
+        let entities = params
+            .as_object()
+            .ok_or("GameParams is not an object keyed by entity name")?;
+
+        let mut pairs = Vec::with_capacity(entities.len());
+        for (name, data) in entities {
+            pairs.push((string_value(name), json_to_value(data, 1)?));
+        }
+        Ok(PyValue::Tuple(vec![PyValue::Dict(pairs)]))
+    }
+
+    /// Pickle a value with protocol 1, protocol 2 is only used for NEWOBJ and 4 for NEWOBJ_EX
+    pub fn pickle(value: &PyValue) -> UnpackResult<Vec<u8>> {
+        let mut pickler = Pickler::default();
+        pickler.save(value, 0)?;
+        pickler.output.push(b'.');
+
+        if pickler.protocol >= 2 {
+            let mut output = vec![0x80, pickler.protocol];
+            output.append(&mut pickler.output);
+            return Ok(output);
+        }
+        Ok(pickler.output)
+    }
+
+    /// Compress the pickle data and reverse the compressed data
+    pub fn deflate(pickled: &[u8]) -> UnpackResult<Vec<u8>> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(pickled)?;
+        let mut data = encoder.finish()?;
+        data.reverse();
+        Ok(data)
+    }
+
+    /// Encode a params tree and write it as GameParams.data
+    pub fn write_to_file(params: &JsonValue, file_path: &str) -> UnpackResult<()> {
+        if let Some(parent) = Path::new(file_path).parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        let data = Self::encode_json(params)?;
+        File::create(file_path)?.write_all(&data)?;
+        info!("GameParams encoded to {}", file_path);
+        Ok(())
+    }
+}
+
+/// Python 2 str for ascii like the game, unicode otherwise
+fn string_value(text: &str) -> PyValue {
+    if text.is_ascii() {
+        PyValue::Bytes(text.as_bytes().to_vec())
+    } else {
+        PyValue::Str(text.to_string())
+    }
+}
+
+fn global(module: &str, name: &str) -> PyValue {
+    PyValue::Global {
+        module: module.to_string(),
+        name: name.to_string(),
+    }
+}
+
+fn json_to_value(value: &JsonValue, depth: usize) -> UnpackResult<PyValue> {
+    if depth > MAX_DEPTH {
+        return Err(Box::from("GameParams is nested too deeply"));
+    }
+
+    let value = match value {
+        JsonValue::Null => PyValue::None,
+        JsonValue::Bool(value) => PyValue::Bool(*value),
+        JsonValue::Number(number) => {
+            if let Some(value) = number.as_i64() {
+                PyValue::Int(value)
+            } else if let Some(value) = number.as_u64() {
+                PyValue::Long(value as i128)
+            } else {
+                PyValue::Float(number.as_f64().unwrap_or(f64::NAN))
+            }
+        }
+        JsonValue::String(text) => string_value(text),
+        JsonValue::Array(items) => PyValue::List(
+            items
+                .iter()
+                .map(|item| json_to_value(item, depth + 1))
+                .collect::<UnpackResult<_>>()?,
+        ),
+        JsonValue::Object(map) => {
+            let mut state = Vec::with_capacity(map.len());
+            for (key, value) in map {
+                state.push((string_value(key), json_to_value(value, depth + 1)?));
+            }
+            // copy_reg._reconstructor(GameParams.GPData, object, None) and BUILD with the state
+            PyValue::Object(Box::new(PyObject {
+                kind: ObjectKind::Reduce,
+                callable: global("copy_reg", "_reconstructor"),
+                args: PyValue::Tuple(vec![
+                    global("GameParams", "GPData"),
+                    global("__builtin__", "object"),
+                    PyValue::None,
+                ]),
+                kwargs: None,
+                state: Some(PyValue::Dict(state)),
+                list_items: Vec::new(),
+                dict_items: Vec::new(),
+            }))
+        }
+    };
+    Ok(value)
+}
+
+#[derive(Default)]
+struct Pickler {
+    output: Vec<u8>,
+    protocol: u8,
+    /// The ids of the Shared values written so far
+    memo: HashSet<u64>,
+}
+
+impl Pickler {
+    fn write_line(&mut self, opcode: u8, line: &str) {
+        self.output.push(opcode);
+        self.output.extend_from_slice(line.as_bytes());
+        self.output.push(b'\n');
+    }
+
+    fn save_items(&mut self, items: &[PyValue], depth: usize) -> UnpackResult<()> {
+        for item in items {
+            self.save(item, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn save_pairs(&mut self, pairs: &[(PyValue, PyValue)], depth: usize) -> UnpackResult<()> {
+        for (key, value) in pairs {
+            self.save(key, depth + 1)?;
+            self.save(value, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// MARK items APPENDS on the list or object on the stack
+    fn appends(&mut self, items: &[PyValue], depth: usize) -> UnpackResult<()> {
+        if !items.is_empty() {
+            self.output.push(b'(');
+            self.save_items(items, depth)?;
+            self.output.push(b'e');
+        }
+        Ok(())
+    }
+
+    /// MARK pairs SETITEMS on the dict or object on the stack
+    fn set_items(&mut self, pairs: &[(PyValue, PyValue)], depth: usize) -> UnpackResult<()> {
+        if !pairs.is_empty() {
+            self.output.push(b'(');
+            self.save_pairs(pairs, depth)?;
+            self.output.push(b'u');
+        }
+        Ok(())
+    }
+
+    fn save_global(&mut self, opcode: u8, module: &str, name: &str) {
+        self.write_line(opcode, module);
+        self.output.extend_from_slice(name.as_bytes());
+        self.output.push(b'\n');
+    }
+
+    /// BINPUT or LONG_BINPUT of a Shared value
+    fn put(&mut self, id: Option<u64>) -> UnpackResult<()> {
+        let id = match id {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+        match u8::try_from(id) {
+            Ok(id) => self.output.extend_from_slice(&[b'q', id]),
+            Err(_) => {
+                self.output.push(b'r');
+                self.output
+                    .extend_from_slice(&u32::try_from(id)?.to_le_bytes());
+            }
+        }
+        self.memo.insert(id);
+        Ok(())
+    }
+
+    /// BINGET or LONG_BINGET of a Ref
+    fn get(&mut self, id: u64) -> UnpackResult<()> {
+        if !self.memo.contains(&id) {
+            return Err(Box::from(format!(
+                "Ref {} comes before its Shared value",
+                id
+            )));
+        }
+        match u8::try_from(id) {
+            Ok(id) => self.output.extend_from_slice(&[b'h', id]),
+            Err(_) => {
+                self.output.push(b'j');
+                self.output
+                    .extend_from_slice(&u32::try_from(id)?.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&mut self, value: &PyValue, depth: usize) -> UnpackResult<()> {
+        if depth > MAX_DEPTH {
+            return Err(Box::from("GameParams is nested too deeply"));
+        }
+
+        match value {
+            PyValue::Ref(id) => self.get(*id),
+            PyValue::Shared { id, value } => self.save_value(value, depth, Some(*id)),
+            value => self.save_value(value, depth, None),
+        }
+    }
+
+    /**
+     * Write a value, containers are memoized right after they are created so they can contain themselves
+     * @param memo The id of a Shared value
+     */
+    fn save_value(&mut self, value: &PyValue, depth: usize, memo: Option<u64>) -> UnpackResult<()> {
+        match value {
+            PyValue::None => self.output.push(b'N'),
+            PyValue::Bool(value) => self.write_line(b'I', if *value { "01" } else { "00" }),
+            PyValue::Int(value) => match *value {
+                0..=0xff => self.output.extend_from_slice(&[b'K', *value as u8]),
+                0x100..=0xffff => {
+                    self.output.push(b'M');
+                    self.output
+                        .extend_from_slice(&(*value as u16).to_le_bytes());
+                }
+                value if i32::try_from(value).is_ok() => {
+                    self.output.push(b'J');
+                    self.output.extend_from_slice(&(value as i32).to_le_bytes());
+                }
+                value => self.write_line(b'I', &value.to_string()),
+            },
+            PyValue::Long(value) => self.write_line(b'L', &format!("{}L", value)),
+            PyValue::Float(value) => {
+                self.output.push(b'G');
+                self.output.extend_from_slice(&value.to_be_bytes());
+            }
+            PyValue::Bytes(data) => {
+                if data.len() < 0x100 {
+                    self.output.extend_from_slice(&[b'U', data.len() as u8]);
+                } else {
+                    self.output.push(b'T');
+                    let size = u32::try_from(data.len())?;
+                    self.output.extend_from_slice(&size.to_le_bytes());
+                }
+                self.output.extend_from_slice(data);
+            }
+            PyValue::Str(text) => {
+                self.output.push(b'X');
+                let size = u32::try_from(text.len())?;
+                self.output.extend_from_slice(&size.to_le_bytes());
+                self.output.extend_from_slice(text.as_bytes());
+            }
+            PyValue::Tuple(items) => {
+                if items.is_empty() {
+                    self.output.push(b')');
+                } else {
+                    self.output.push(b'(');
+                    self.save_items(items, depth)?;
+                    self.output.push(b't');
+                }
+            }
+            PyValue::List(items) => {
+                self.output.push(b']');
+                self.put(memo)?;
+                return self.appends(items, depth);
+            }
+            PyValue::Dict(pairs) => {
+                self.output.push(b'}');
+                self.put(memo)?;
+                return self.set_items(pairs, depth);
+            }
+            // set([items]) and frozenset([items]) like Python 2
+            PyValue::Set(items) | PyValue::FrozenSet(items) => {
+                let name = match value {
+                    PyValue::Set(_) => "set",
+                    _ => "frozenset",
+                };
+                self.save_global(b'c', "__builtin__", name);
+                self.output.extend_from_slice(b"(]");
+                self.appends(items, depth)?;
+                self.output.extend_from_slice(b"tR");
+            }
+            PyValue::Global { module, name } => self.save_global(b'c', module, name),
+            PyValue::Object(object) => return self.save_object(object, depth, memo),
+            PyValue::Shared { .. } | PyValue::Ref(_) => {
+                return Err(Box::from("A Shared value is directly inside another one"))
+            }
+        }
+        self.put(memo)
+    }
+
+    fn save_object(
+        &mut self,
+        object: &PyObject,
+        depth: usize,
+        memo: Option<u64>,
+    ) -> UnpackResult<()> {
+        let args = match &object.args {
+            PyValue::Tuple(args) => args.as_slice(),
+            _ => return Err(Box::from("Object arguments are not a tuple")),
+        };
+
+        match object.kind {
+            ObjectKind::Reduce => {
+                self.save(&object.callable, depth + 1)?;
+                self.save(&object.args, depth + 1)?;
+                self.output.push(b'R');
+            }
+            ObjectKind::NewObj => match &object.kwargs {
+                // NEWOBJ_EX
+                Some(kwargs) => {
+                    self.protocol = self.protocol.max(4);
+                    self.save(&object.callable, depth + 1)?;
+                    self.save(&object.args, depth + 1)?;
+                    self.save(kwargs, depth + 1)?;
+                    self.output.push(0x92);
+                }
+                None => {
+                    self.protocol = self.protocol.max(2);
+                    self.save(&object.callable, depth + 1)?;
+                    self.save(&object.args, depth + 1)?;
+                    self.output.push(0x81);
+                }
+            },
+            ObjectKind::Inst => match &object.callable {
+                PyValue::Global { module, name } => {
+                    self.output.push(b'(');
+                    self.save_items(args, depth)?;
+                    self.save_global(b'i', module, name);
+                }
+                callable => {
+                    self.output.push(b'(');
+                    self.save(callable, depth + 1)?;
+                    self.save_items(args, depth)?;
+                    self.output.push(b'o');
+                }
+            },
+        }
+
+        self.put(memo)?;
+        self.appends(&object.list_items, depth)?;
+        self.set_items(&object.dict_items, depth)?;
+        if let Some(state) = &object.state {
+            self.save(state, depth + 1)?;
+            self.output.push(b'b');
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParamsEncoder;
+    use crate::unpack::params_decode::ParamsDecoder;
+    use crate::unpack::params_value::{PyObject, PyValue};
+    use crate::utils::pickle::ObjectKind;
+    use serde_json::json;
+
+    #[test]
+    fn test_encode_json_round_trip() {
+        let params = json!({
+            "PASB017_Montana_1945": {
+                "id": 4181604048i64,
+                "index": "PASB017",
+                "typeinfo": {"nation": "USA", "species": "Battleship", "type": "Ship"},
+                "A_Artillery": {"HP_AGM_1": {"maxDist": 23.6, "ammoList": ["PAPA001", null]}},
+                "numbers": [0, 255, 256, 65536, -1, 18446744073709551615u64, 1e300],
+                "flags": [true, false],
+                "name": "Montana \u{e9}",
+                "empty": {"list": [], "object": {}},
+            },
+        });
+
+        let data = ParamsEncoder::encode_json(&params).unwrap();
+        assert_eq!(ParamsDecoder::decode(&data).unwrap(), params);
+    }
+
+    #[test]
+    fn test_encode_lossless_round_trip() {
+        let object = PyObject {
+            kind: ObjectKind::Inst,
+            callable: PyValue::Global {
+                module: "GameParams".to_string(),
+                name: "Old".to_string(),
+            },
+            args: PyValue::Tuple(vec![PyValue::Int(1)]),
+            kwargs: None,
+            state: Some(PyValue::None),
+            list_items: vec![PyValue::Bytes(vec![0xff; 300])],
+            dict_items: vec![(PyValue::Int(-70000), PyValue::FrozenSet(vec![]))],
+        };
+        let root = PyValue::Tuple(vec![
+            PyValue::Object(Box::new(object)),
+            PyValue::Set(vec![PyValue::Long(1 << 70), PyValue::Float(f64::INFINITY)]),
+            PyValue::Tuple(vec![]),
+            PyValue::Object(Box::new(PyObject {
+                kind: ObjectKind::NewObj,
+                callable: PyValue::Global {
+                    module: "collections".to_string(),
+                    name: "OrderedDict".to_string(),
+                },
+                args: PyValue::Tuple(vec![]),
+                kwargs: None,
+                state: None,
+                list_items: vec![],
+                dict_items: vec![(PyValue::Str("a".to_string()), PyValue::Bool(true))],
+            })),
+        ]);
+
+        let data = ParamsEncoder::encode(&root).unwrap();
+        assert_eq!(ParamsDecoder::decode_lossless(&data).unwrap(), root);
+
+        assert!(ParamsEncoder::encode_json(&json!([1])).is_err());
+    }
+
+    #[test]
+    fn test_encode_shared_round_trip() {
+        let kwargs = PyValue::Dict(vec![(PyValue::Str("b".to_string()), PyValue::Int(2))]);
+        let object = PyObject {
+            kind: ObjectKind::NewObj,
+            callable: PyValue::Global {
+                module: "GameParams".to_string(),
+                name: "K".to_string(),
+            },
+            args: PyValue::Tuple(vec![]),
+            kwargs: Some(PyValue::Shared {
+                id: 2,
+                value: Box::new(kwargs),
+            }),
+            state: None,
+            list_items: vec![],
+            dict_items: vec![(PyValue::Str("self".to_string()), PyValue::Ref(1))],
+        };
+        let root = PyValue::Tuple(vec![
+            PyValue::Shared {
+                id: 0,
+                value: Box::new(PyValue::List(vec![PyValue::Int(1), PyValue::Ref(0)])),
+            },
+            PyValue::Shared {
+                id: 1,
+                value: Box::new(PyValue::Object(Box::new(object))),
+            },
+            PyValue::Ref(0),
+            PyValue::Ref(2),
+        ]);
+
+        let data = ParamsEncoder::encode(&root).unwrap();
+        assert_eq!(ParamsDecoder::decode_lossless(&data).unwrap(), root);
+
+        // the decoder numbers the shared values in order, ids above 255 use LONG_BINPUT
+        let list = |id| PyValue::Shared {
+            id,
+            value: Box::new(PyValue::List(vec![])),
+        };
+        let data =
+            ParamsEncoder::encode(&PyValue::Tuple(vec![list(300), PyValue::Ref(300)])).unwrap();
+        assert_eq!(
+            ParamsDecoder::decode_lossless(&data).unwrap(),
+            PyValue::Tuple(vec![list(0), PyValue::Ref(0)])
+        );
+        assert!(ParamsEncoder::encode(&PyValue::Ref(0)).is_err());
+    }
+}
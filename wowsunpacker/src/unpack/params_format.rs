@@ -0,0 +1,137 @@
+// Output formats of the decoded GameParams, every format loads back into the same json tree
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use log::info;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::types::UnpackResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum ParamsFormat {
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl ParamsFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ParamsFormat::Json => "json",
+            ParamsFormat::MessagePack => "msgpack",
+            ParamsFormat::Cbor => "cbor",
+        }
+    }
+
+    /// Guess the format from the file extension, e.g. GameParams.msgpack
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        extension.parse().ok()
+    }
+
+    /**
+     * Write the params tree in this format
+     * @param params The decoded params
+     * @param file_path The output file, parent directories are created
+     * @param compact Whether to skip pretty printing, only used by json
+     */
+    pub fn write(&self, params: &JsonValue, file_path: &str, compact: bool) -> UnpackResult<()> {
+        if let Some(parent) = Path::new(file_path).parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        match self {
+            ParamsFormat::Json if compact => serde_json::to_writer(&mut writer, params)?,
+            ParamsFormat::Json => serde_json::to_writer_pretty(&mut writer, params)?,
+            ParamsFormat::MessagePack => rmp_serde::encode::write(&mut writer, params)?,
+            ParamsFormat::Cbor => ciborium::ser::into_writer(params, &mut writer)?,
+        }
+        // dropping the BufWriter would ignore a failed write
+        writer.flush()?;
+        info!("GameParams written to {} as {:?}", file_path, self);
+        Ok(())
+    }
+
+    /// Load a params tree written in this format
+    pub fn read(&self, file_path: &str) -> UnpackResult<JsonValue> {
+        let reader = BufReader::new(File::open(file_path)?);
+        let params = match self {
+            ParamsFormat::Json => serde_json::from_reader(reader)?,
+            ParamsFormat::MessagePack => rmp_serde::from_read(reader)?,
+            ParamsFormat::Cbor => ciborium::de::from_reader(reader)?,
+        };
+        Ok(params)
+    }
+}
+
+impl FromStr for ParamsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ParamsFormat::Json),
+            "msgpack" | "messagepack" | "mp" => Ok(ParamsFormat::MessagePack),
+            "cbor" => Ok(ParamsFormat::Cbor),
+            _ => Err(format!("Unknown GameParams format {}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for ParamsFormat {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParamsFormat;
+    use crate::unpack::{params_model::GameParams, test_utils::temp_dir};
+    use serde_json::json;
+
+    #[test]
+    fn test_formats_round_trip() {
+        let params = json!({
+            "PASB017_Montana_1945": {
+                "id": 4181604048i64,
+                "index": "PASB017",
+                "typeinfo": {"nation": "USA", "species": "Battleship", "type": "Ship"},
+                "A_Artillery": {"HP_AGM_1": {"maxDist": 23.6, "ammoList": ["PAPA001", null]}},
+                "negative": -12,
+            },
+        });
+
+        let dest = temp_dir("params_format");
+        for format in [
+            ParamsFormat::Json,
+            ParamsFormat::MessagePack,
+            ParamsFormat::Cbor,
+        ] {
+            let path = format!("{}/GameParams.{}", dest.display(), format.extension());
+            format.write(&params, &path, true).unwrap();
+            assert_eq!(ParamsFormat::from_path(&path), Some(format));
+
+            let loaded = GameParams::from_file(&path).unwrap();
+            assert_eq!(loaded.root(), &params);
+            assert_eq!(
+                loaded.get_by_index("PASB017").unwrap().name,
+                "PASB017_Montana_1945"
+            );
+        }
+
+        assert_eq!("CBOR".parse(), Ok(ParamsFormat::Cbor));
+        assert!("xml".parse::<ParamsFormat>().is_err());
+    }
+}
@@ -0,0 +1,243 @@
+// Attach the localized names of global.mo to GameParams entities
+// The game looks up IDS_<index>_FULL, IDS_<index> and IDS_<index>_DESCR, e.g. IDS_PASB017
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::Write,
+    path::Path,
+};
+
+use log::info;
+use serde::Serialize;
+
+use crate::types::UnpackResult;
+use crate::unpack::lang_unpack::LangUnpacker;
+use crate::unpack::params_model::{Entity, GameParams, TypeInfoFilter};
+use crate::utils::game::GameLanguages;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LocalizedNames {
+    pub name: Option<String>,
+    pub short_name: Option<String>,
+    pub description: Option<String>,
+}
+
+impl LocalizedNames {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.short_name.is_none() && self.description.is_none()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocalizedEntity {
+    pub index: String,
+    /// language folder -> names, languages without any string are left out
+    pub names: BTreeMap<String, LocalizedNames>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct LocalizedParams {
+    pub entities: BTreeMap<String, LocalizedEntity>,
+    /// language folder -> entities without a name in that language
+    pub missing: BTreeMap<String, Vec<String>>,
+}
+
+impl LocalizedParams {
+    pub fn write_to_file(&self, file_name: &str, dest: &str) -> UnpackResult<()> {
+        let file_path = Path::new(&dest).join(file_name);
+        let mut file = File::create(file_path)?;
+        let json = serde_json::to_string_pretty(&self)?;
+        file.write_all(json.as_bytes())?;
+        info!("Localized GameParams written to {}/{}", dest, file_name);
+        Ok(())
+    }
+}
+
+/// The IDS_ keys of an entity index, (name, short name, description)
+pub fn ids_keys(index: &str) -> (String, String, String) {
+    let index = index.to_uppercase();
+    (
+        format!("IDS_{}_FULL", index),
+        format!("IDS_{}", index),
+        format!("IDS_{}_DESCR", index),
+    )
+}
+
+#[derive(Default)]
+pub struct ParamsLocalizer<'a> {
+    tables: Vec<(GameLanguages, &'a HashMap<String, String>)>,
+}
+
+impl<'a> ParamsLocalizer<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Add the strings of a language
+     * @param language The language of the table
+     * @param unpacker A decoded global.mo
+     */
+    pub fn add(
+        &mut self,
+        language: &GameLanguages,
+        unpacker: &'a LangUnpacker,
+    ) -> UnpackResult<&mut Self> {
+        if !unpacker.is_decoded() {
+            return Err(Box::from(format!(
+                "Language {} is not decoded yet, call decode() before localizing",
+                language
+            )));
+        }
+
+        self.tables.retain(|(lang, _)| lang != language);
+        self.tables.push((*language, unpacker.text_data()));
+        Ok(self)
+    }
+
+    /// The names of an index in one language, the short name is used when there is no full name
+    pub fn names(&self, index: &str, language: &GameLanguages) -> LocalizedNames {
+        let strings = match self.tables.iter().find(|(lang, _)| lang == language) {
+            Some((_, strings)) => strings,
+            None => return LocalizedNames::default(),
+        };
+
+        let (name_key, short_key, description_key) = ids_keys(index);
+        let short_name = strings.get(&short_key).cloned();
+        LocalizedNames {
+            name: strings
+                .get(&name_key)
+                .cloned()
+                .or_else(|| short_name.clone()),
+            short_name,
+            description: strings.get(&description_key).cloned(),
+        }
+    }
+
+    /// The names of an entity in every added language
+    pub fn localize(&self, entity: &Entity) -> BTreeMap<String, LocalizedNames> {
+        let mut names = BTreeMap::new();
+        if let Some(index) = entity.index() {
+            for (language, _) in &self.tables {
+                let localized = self.names(index, language);
+                if !localized.is_empty() {
+                    names.insert(language.to_folder_string(), localized);
+                }
+            }
+        }
+        names
+    }
+
+    /// Localize every entity with an index
+    pub fn join(&self, params: &GameParams) -> LocalizedParams {
+        self.join_entities(params.entities())
+    }
+
+    /// Localize the entities matching the filter, e.g. only ships
+    pub fn join_filtered(&self, params: &GameParams, filter: &TypeInfoFilter) -> LocalizedParams {
+        self.join_entities(params.filter(filter))
+    }
+
+    fn join_entities<'b>(&self, entities: impl Iterator<Item = Entity<'b>>) -> LocalizedParams {
+        let mut localized = LocalizedParams::default();
+        for (language, _) in &self.tables {
+            localized
+                .missing
+                .insert(language.to_folder_string(), Vec::new());
+        }
+
+        for entity in entities {
+            let index = match entity.index() {
+                Some(index) => index,
+                None => continue,
+            };
+
+            let names = self.localize(&entity);
+            for (language, missing) in localized.missing.iter_mut() {
+                let has_name = matches!(
+                    names.get(language),
+                    Some(LocalizedNames { name: Some(_), .. })
+                );
+                if !has_name {
+                    missing.push(entity.name.to_string());
+                }
+            }
+            localized.entities.insert(
+                entity.name.to_string(),
+                LocalizedEntity {
+                    index: index.to_string(),
+                    names,
+                },
+            );
+        }
+
+        for missing in localized.missing.values_mut() {
+            missing.sort();
+        }
+        info!(
+            "Localized {} GameParams entities in {} languages",
+            localized.entities.len(),
+            self.tables.len()
+        );
+        localized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ids_keys, ParamsLocalizer};
+    use crate::unpack::params_model::{GameParams, TypeInfoFilter};
+    use crate::unpack::{lang_unpack::LangUnpacker, test_utils::write_mo_file};
+    use crate::utils::game::GameLanguages;
+    use serde_json::json;
+
+    #[test]
+    fn test_localize_params() {
+        let en = write_mo_file(
+            "localize_en.mo",
+            &[
+                ("IDS_PASB017", "Montana"),
+                ("IDS_PASB017_FULL", "Montana 1945"),
+                ("IDS_PASB017_DESCR", "A battleship"),
+                ("IDS_PJSB018", "Yamato"),
+            ],
+        );
+        let ja = write_mo_file("localize_ja.mo", &[("IDS_PASB017", "モンタナ")]);
+        let mut en_unpacker = LangUnpacker::new(en).unwrap();
+        en_unpacker.decode().unwrap();
+        let mut ja_unpacker = LangUnpacker::new(ja).unwrap();
+        ja_unpacker.decode().unwrap();
+
+        let params = GameParams::new(json!({
+            "PASB017_Montana_1945": {"index": "PASB017", "typeinfo": {"type": "Ship"}},
+            "PJSB018_Yamato_1944": {"index": "PJSB018", "typeinfo": {"type": "Ship"}},
+            "PAPA001_Shell": {"index": "PAPA001", "typeinfo": {"type": "Projectile"}},
+        }))
+        .unwrap();
+
+        let mut localizer = ParamsLocalizer::new();
+        localizer.add(&GameLanguages::EN, &en_unpacker).unwrap();
+        localizer.add(&GameLanguages::JA, &ja_unpacker).unwrap();
+
+        let montana = localizer.names("PASB017", &GameLanguages::EN);
+        assert_eq!(montana.name.as_deref(), Some("Montana 1945"));
+        assert_eq!(montana.short_name.as_deref(), Some("Montana"));
+        assert_eq!(montana.description.as_deref(), Some("A battleship"));
+        // the short name is the name when there is no full name
+        let montana_ja = localizer.names("PASB017", &GameLanguages::JA);
+        assert_eq!(montana_ja.name.as_deref(), Some("モンタナ"));
+        assert!(montana_ja.description.is_none());
+
+        let ships = localizer.join_filtered(&params, &TypeInfoFilter::new().kind("Ship"));
+        assert_eq!(ships.entities.len(), 2);
+        assert_eq!(ships.missing["en"], Vec::<String>::new());
+        assert_eq!(ships.missing["ja"], vec!["PJSB018_Yamato_1944"]);
+
+        let all = localizer.join(&params);
+        assert_eq!(all.missing["en"], vec!["PAPA001_Shell"]);
+        assert!(all.entities["PAPA001_Shell"].names.is_empty());
+
+        assert_eq!(ids_keys("pasb017").1, "IDS_PASB017");
+    }
+}
@@ -0,0 +1,294 @@
+// In-memory model of the decoded GameParams with lookups by name, id, index and typeinfo
+
+use std::collections::HashMap;
+
+use log::info;
+use serde::Deserialize;
+use serde_json::{Map, Value as JsonValue};
+
+use crate::types::UnpackResult;
+use crate::unpack::game_unpack::GameUnpacker;
+use crate::unpack::params_format::ParamsFormat;
+
+/// The typeinfo of every entity, e.g. {"nation": "USA", "species": "Battleship", "type": "Ship"}
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct TypeInfo {
+    pub nation: Option<String>,
+    pub species: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+/// Select entities by their typeinfo, unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct TypeInfoFilter {
+    kind: Option<String>,
+    species: Option<String>,
+    nation: Option<String>,
+}
+
+impl TypeInfoFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn kind(mut self, kind: &str) -> Self {
+        self.kind = Some(kind.to_string());
+        self
+    }
+
+    pub fn species(mut self, species: &str) -> Self {
+        self.species = Some(species.to_string());
+        self
+    }
+
+    pub fn nation(mut self, nation: &str) -> Self {
+        self.nation = Some(nation.to_string());
+        self
+    }
+
+    pub fn matches(&self, type_info: &TypeInfo) -> bool {
+        let field_matches = |expected: &Option<String>, actual: &Option<String>| match expected {
+            Some(expected) => actual.as_deref() == Some(expected.as_str()),
+            None => true,
+        };
+        field_matches(&self.kind, &type_info.kind)
+            && field_matches(&self.species, &type_info.species)
+            && field_matches(&self.nation, &type_info.nation)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Entity<'a> {
+    pub name: &'a str,
+    pub data: &'a JsonValue,
+    /// Parsed once by GameParams::new()
+    type_info: &'a TypeInfo,
+}
+
+impl<'a> Entity<'a> {
+    pub fn id(&self) -> Option<i64> {
+        self.data.get("id")?.as_i64()
+    }
+
+    /// The short name used by the game, e.g. PASB017
+    pub fn index(&self) -> Option<&'a str> {
+        self.data.get("index")?.as_str()
+    }
+
+    /// The parsed typeinfo, empty when the entity has none
+    pub fn type_info(&self) -> &'a TypeInfo {
+        self.type_info
+    }
+
+    /// Query a value inside the entity with a json pointer, e.g. /A_Artillery/HP_AGM_1/maxDist
+    pub fn pointer(&self, pointer: &str) -> Option<&'a JsonValue> {
+        self.data.pointer(pointer)
+    }
+}
+
+pub struct GameParams {
+    root: JsonValue,
+    by_id: HashMap<i64, String>,
+    by_index: HashMap<String, String>,
+    type_infos: HashMap<String, TypeInfo>,
+}
+
+impl GameParams {
+    /**
+     * Build the model from the decoded params
+     * @param root The params tree keyed by entity name
+     */
+    pub fn new(root: JsonValue) -> UnpackResult<Self> {
+        let entities = root
+            .as_object()
+            .ok_or("GameParams is not an object keyed by entity name")?;
+
+        let mut by_id = HashMap::new();
+        let mut by_index = HashMap::new();
+        let mut type_infos = HashMap::new();
+        for (name, data) in entities {
+            let type_info = data
+                .get("typeinfo")
+                .and_then(|type_info| TypeInfo::deserialize(type_info).ok())
+                .unwrap_or_default();
+            let entity = Entity {
+                name,
+                data,
+                type_info: &type_info,
+            };
+            if let Some(id) = entity.id() {
+                by_id.insert(id, name.clone());
+            }
+            if let Some(index) = entity.index() {
+                by_index.insert(index.to_string(), name.clone());
+            }
+            type_infos.insert(name.clone(), type_info);
+        }
+
+        info!("Loaded {} GameParams entities", entities.len());
+        Ok(Self {
+            root,
+            by_id,
+            by_index,
+            type_infos,
+        })
+    }
+
+    /// Decode content/GameParams.data of the game
+    pub fn from_unpacker(unpacker: &GameUnpacker) -> UnpackResult<Self> {
+        Self::new(unpacker.decode_params()?)
+    }
+
+    /// Load a GameParams.json written by the C# or native decoder
+    pub fn from_json_file(path: &str) -> UnpackResult<Self> {
+        Self::from_file_as(path, ParamsFormat::Json)
+    }
+
+    /// Load json, MessagePack or CBOR params, the format comes from the file extension
+    pub fn from_file(path: &str) -> UnpackResult<Self> {
+        let format = ParamsFormat::from_path(path)
+            .ok_or_else(|| format!("Unknown GameParams format of {}", path))?;
+        Self::from_file_as(path, format)
+    }
+
+    pub fn from_file_as(path: &str, format: ParamsFormat) -> UnpackResult<Self> {
+        Self::new(format.read(path)?)
+    }
+
+    pub fn root(&self) -> &JsonValue {
+        &self.root
+    }
+
+    fn entity<'a>(&'a self, name: &'a String, data: &'a JsonValue) -> Entity<'a> {
+        Entity {
+            name,
+            data,
+            // every entity got a typeinfo in new()
+            type_info: &self.type_infos[name],
+        }
+    }
+
+    fn entity_map(&self) -> &Map<String, JsonValue> {
+        // validated in new()
+        self.root
+            .as_object()
+            .expect("GameParams root is not an object")
+    }
+
+    pub fn len(&self) -> usize {
+        self.entity_map().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entity_map().is_empty()
+    }
+
+    /// Get an entity by its name, e.g. PASB017_Montana_1945
+    pub fn get(&self, name: &str) -> Option<Entity<'_>> {
+        let (name, data) = self.entity_map().get_key_value(name)?;
+        Some(self.entity(name, data))
+    }
+
+    pub fn get_by_id(&self, id: i64) -> Option<Entity<'_>> {
+        self.get(self.by_id.get(&id)?)
+    }
+
+    /// Get an entity by its index, e.g. PASB017
+    pub fn get_by_index(&self, index: &str) -> Option<Entity<'_>> {
+        self.get(self.by_index.get(index)?)
+    }
+
+    /// Query with a json pointer from the root, e.g. /PASB017_Montana_1945/typeinfo/nation
+    pub fn query(&self, pointer: &str) -> Option<&JsonValue> {
+        self.root.pointer(pointer)
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity<'_>> {
+        self.entity_map()
+            .iter()
+            .map(|(name, data)| self.entity(name, data))
+    }
+
+    /// All entities of a typeinfo type, e.g. Ship or Modernization
+    pub fn of_type<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = Entity<'a>> {
+        self.entities()
+            .filter(move |entity| entity.type_info().kind.as_deref() == Some(kind))
+    }
+
+    pub fn filter<'a>(&'a self, filter: &'a TypeInfoFilter) -> impl Iterator<Item = Entity<'a>> {
+        self.entities()
+            .filter(move |entity| filter.matches(entity.type_info()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameParams, TypeInfoFilter};
+    use serde_json::json;
+
+    fn sample_params() -> GameParams {
+        GameParams::new(json!({
+            "PASB017_Montana_1945": {
+                "id": 4181604048i64,
+                "index": "PASB017",
+                "name": "PASB017_Montana_1945",
+                "typeinfo": {"nation": "USA", "species": "Battleship", "type": "Ship"},
+                "A_Artillery": {"HP_AGM_1": {"maxDist": 23.6}},
+            },
+            "PJSB018_Yamato_1944": {
+                "id": 4181604049i64,
+                "index": "PJSB018",
+                "name": "PJSB018_Yamato_1944",
+                "typeinfo": {"nation": "Japan", "species": "Battleship", "type": "Ship"},
+            },
+            "PCM001_MainGun_Mod_I": {
+                "id": 4290768816i64,
+                "index": "PCM001",
+                "name": "PCM001_MainGun_Mod_I",
+                "typeinfo": {"nation": "Common", "species": null, "type": "Modernization"},
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lookup() {
+        let params = sample_params();
+        assert_eq!(params.len(), 3);
+        let montana = params.get("PASB017_Montana_1945").unwrap();
+        assert_eq!(montana.index(), Some("PASB017"));
+        assert_eq!(montana.type_info().nation.as_deref(), Some("USA"));
+        assert_eq!(
+            montana.pointer("/A_Artillery/HP_AGM_1/maxDist"),
+            Some(&json!(23.6))
+        );
+
+        assert_eq!(
+            params.get_by_id(4181604049).unwrap().name,
+            "PJSB018_Yamato_1944"
+        );
+        assert_eq!(
+            params.get_by_index("PCM001").unwrap().name,
+            "PCM001_MainGun_Mod_I"
+        );
+        assert_eq!(
+            params.query("/PJSB018_Yamato_1944/typeinfo/nation"),
+            Some(&json!("Japan"))
+        );
+        assert!(params.get("PZSB999").is_none());
+    }
+
+    #[test]
+    fn test_filter() {
+        let params = sample_params();
+        assert_eq!(params.of_type("Ship").count(), 2);
+        assert_eq!(params.of_type("Modernization").count(), 1);
+
+        let filter = TypeInfoFilter::new().kind("Ship").nation("Japan");
+        let ships: Vec<&str> = params.filter(&filter).map(|entity| entity.name).collect();
+        assert_eq!(ships, vec!["PJSB018_Yamato_1944"]);
+
+        assert!(GameParams::new(json!([1, 2])).is_err());
+    }
+}
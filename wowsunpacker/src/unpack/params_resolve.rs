@@ -0,0 +1,352 @@
+// Follow the references between GameParams entities, e.g. the ammo of a gun or the consumables of a ship
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::Write,
+    path::Path,
+};
+
+use log::info;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::types::UnpackResult;
+use crate::unpack::params_model::{Entity, GameParams};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceKind {
+    /// a shell or torpedo in ammoList
+    Ammo,
+    /// a consumable in ShipAbilities
+    Ability,
+    /// a ship in nextShips or in the ships of a modernization
+    Ship,
+    /// a section of the same entity in ShipUpgradeInfo components, e.g. A_Artillery
+    Component,
+    /// another upgrade of the same ShipUpgradeInfo in prev
+    Upgrade,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Reference {
+    /// json pointer inside the entity, e.g. /A_Artillery/HP_AGM_1/ammoList/0
+    pub path: String,
+    pub kind: ReferenceKind,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DanglingReference {
+    pub entity: String,
+    #[serde(flatten)]
+    pub reference: Reference,
+}
+
+/// A ship with its ammo and consumables inlined
+#[derive(Debug, Serialize)]
+pub struct ShipView {
+    pub name: String,
+    pub params: JsonValue,
+    /// the modernizations which list this ship
+    pub modernizations: BTreeMap<String, JsonValue>,
+    /// references which could not be inlined
+    pub dangling: Vec<Reference>,
+}
+
+impl ShipView {
+    pub fn write_to_file(&self, file_name: &str, dest: &str) -> UnpackResult<()> {
+        let file_path = Path::new(&dest).join(file_name);
+        let mut file = File::create(file_path)?;
+        let json = serde_json::to_string_pretty(&self)?;
+        file.write_all(json.as_bytes())?;
+        info!(
+            "Ship view of {} written to {}/{}",
+            self.name, dest, file_name
+        );
+        Ok(())
+    }
+}
+
+pub struct ParamsResolver<'a> {
+    params: &'a GameParams,
+    /// ship name -> modernizations
+    modernizations: HashMap<String, Vec<String>>,
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn push_names(items: &JsonValue, path: &str, kind: ReferenceKind, references: &mut Vec<Reference>) {
+    if let JsonValue::Array(items) = items {
+        for (i, item) in items.iter().enumerate() {
+            if let Some(target) = item.as_str() {
+                references.push(Reference {
+                    path: format!("{}/{}", path, i),
+                    kind,
+                    target: target.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Collect the references of a section, the rules follow the layout of the game
+fn collect_references(value: &JsonValue, path: &str, references: &mut Vec<Reference>) {
+    match value {
+        JsonValue::Object(map) => {
+            for (key, value) in map {
+                let child = format!("{}/{}", path, escape_pointer(key));
+                match key.as_str() {
+                    "ammoList" => push_names(value, &child, ReferenceKind::Ammo, references),
+                    "nextShips" => push_names(value, &child, ReferenceKind::Ship, references),
+                    // [["PCY001_CrashCrew", "PCY001_CrashCrew_Premium"], ...]
+                    "abils" => {
+                        let abilities = value.as_array().into_iter().flatten();
+                        for (i, ability) in abilities.enumerate() {
+                            if let Some(target) = ability.get(0).and_then(|name| name.as_str()) {
+                                references.push(Reference {
+                                    path: format!("{}/{}/0", child, i),
+                                    kind: ReferenceKind::Ability,
+                                    target: target.to_string(),
+                                });
+                            }
+                        }
+                    }
+                    _ => collect_references(value, &child, references),
+                }
+            }
+        }
+        JsonValue::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_references(item, &format!("{}/{}", path, i), references);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The components and prev of every upgrade in ShipUpgradeInfo
+fn collect_upgrade_references(upgrades: &JsonValue, references: &mut Vec<Reference>) {
+    let upgrades = match upgrades.as_object() {
+        Some(upgrades) => upgrades,
+        None => return,
+    };
+    for (name, upgrade) in upgrades {
+        let path = format!("/ShipUpgradeInfo/{}", escape_pointer(name));
+        if let Some(components) = upgrade.get("components").and_then(|c| c.as_object()) {
+            for (component, sections) in components {
+                let path = format!("{}/components/{}", path, escape_pointer(component));
+                push_names(sections, &path, ReferenceKind::Component, references);
+            }
+        }
+        if let Some(prev) = upgrade.get("prev").and_then(|prev| prev.as_str()) {
+            if !prev.is_empty() {
+                references.push(Reference {
+                    path: format!("{}/prev", path),
+                    kind: ReferenceKind::Upgrade,
+                    target: prev.to_string(),
+                });
+            }
+        }
+    }
+}
+
+impl<'a> ParamsResolver<'a> {
+    pub fn new(params: &'a GameParams) -> Self {
+        let mut modernizations: HashMap<String, Vec<String>> = HashMap::new();
+        for entity in params.of_type("Modernization") {
+            if let Some(JsonValue::Array(ships)) = entity.data.get("ships") {
+                for ship in ships.iter().filter_map(|ship| ship.as_str()) {
+                    modernizations
+                        .entry(ship.to_string())
+                        .or_default()
+                        .push(entity.name.to_string());
+                }
+            }
+        }
+        Self {
+            params,
+            modernizations,
+        }
+    }
+
+    /// All references of an entity to other entities and to its own sections
+    pub fn references(&self, entity: &Entity) -> Vec<Reference> {
+        let mut references = Vec::new();
+        if let Some(sections) = entity.data.as_object() {
+            for (key, value) in sections {
+                let path = format!("/{}", escape_pointer(key));
+                match key.as_str() {
+                    "ShipUpgradeInfo" => {
+                        collect_upgrade_references(value, &mut references);
+                        collect_references(value, &path, &mut references);
+                    }
+                    "ships" if entity.type_info().kind.as_deref() == Some("Modernization") => {
+                        push_names(value, &path, ReferenceKind::Ship, &mut references)
+                    }
+                    _ => collect_references(value, &path, &mut references),
+                }
+            }
+        }
+        references
+    }
+
+    /// Whether the target of a reference exists
+    pub fn resolves(&self, entity: &Entity, reference: &Reference) -> bool {
+        match reference.kind {
+            ReferenceKind::Ammo | ReferenceKind::Ability | ReferenceKind::Ship => {
+                self.params.get(&reference.target).is_some()
+            }
+            ReferenceKind::Component => entity.data.get(&reference.target).is_some(),
+            ReferenceKind::Upgrade => entity
+                .pointer("/ShipUpgradeInfo")
+                .and_then(|upgrades| upgrades.get(&reference.target))
+                .is_some(),
+        }
+    }
+
+    /// Every reference of every entity whose target does not exist
+    pub fn dangling(&self) -> Vec<DanglingReference> {
+        let mut dangling = Vec::new();
+        for entity in self.params.entities() {
+            for reference in self.references(&entity) {
+                if !self.resolves(&entity, &reference) {
+                    dangling.push(DanglingReference {
+                        entity: entity.name.to_string(),
+                        reference,
+                    });
+                }
+            }
+        }
+        dangling
+            .sort_by(|a, b| (&a.entity, &a.reference.path).cmp(&(&b.entity, &b.reference.path)));
+        info!("Found {} dangling references in GameParams", dangling.len());
+        dangling
+    }
+
+    /**
+     * Build the denormalized view of a ship
+     * @param name The ship name, e.g. PASB017_Montana_1945
+     * @return The ship with the ammo and consumable entities in place of their names
+     */
+    pub fn ship_view(&self, name: &str) -> UnpackResult<ShipView> {
+        let entity = self
+            .params
+            .get(name)
+            .ok_or_else(|| format!("Entity {} does not exist", name))?;
+        if entity.type_info().kind.as_deref() != Some("Ship") {
+            return Err(Box::from(format!("Entity {} is not a ship", name)));
+        }
+
+        let mut params = entity.data.clone();
+        let mut dangling = Vec::new();
+        for reference in self.references(&entity) {
+            if !self.resolves(&entity, &reference) {
+                dangling.push(reference);
+                continue;
+            }
+            if !matches!(reference.kind, ReferenceKind::Ammo | ReferenceKind::Ability) {
+                continue;
+            }
+            if let (Some(value), Some(target)) = (
+                params.pointer_mut(&reference.path),
+                self.params.get(&reference.target),
+            ) {
+                *value = target.data.clone();
+            }
+        }
+
+        let mut modernizations = BTreeMap::new();
+        for modernization in self.modernizations.get(name).into_iter().flatten() {
+            if let Some(entity) = self.params.get(modernization) {
+                modernizations.insert(modernization.clone(), entity.data.clone());
+            }
+        }
+
+        Ok(ShipView {
+            name: name.to_string(),
+            params,
+            modernizations,
+            dangling,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParamsResolver, ReferenceKind};
+    use crate::unpack::params_model::GameParams;
+    use serde_json::json;
+
+    fn sample_params() -> GameParams {
+        GameParams::new(json!({
+            "PASB017_Montana_1945": {
+                "typeinfo": {"nation": "USA", "species": "Battleship", "type": "Ship"},
+                "A_Artillery": {"HP_AGM_1": {"ammoList": ["PAPA001_406mm_HE", "PAPA999_Missing"]}},
+                "A_Torpedoes": {},
+                "ShipAbilities": {"AbilitySlot0": {"abils": [["PCY001_CrashCrew", "PCY001_CrashCrew_Premium"]]}},
+                "ShipUpgradeInfo": {
+                    "AB1_Artillery": {"components": {"artillery": ["A_Artillery"], "torpedoes": ["B_Torpedoes"]}, "prev": ""},
+                    "AB2_Artillery": {"components": {}, "prev": "AB1_Artillery", "nextShips": ["PASB018_Missing"]},
+                },
+            },
+            "PAPA001_406mm_HE": {"typeinfo": {"nation": "USA", "species": "Artillery", "type": "Projectile"}, "alphaDamage": 5700},
+            "PCY001_CrashCrew": {"typeinfo": {"nation": "Common", "species": null, "type": "Ability"}, "numConsumables": -1},
+            "PCM001_MainGun_Mod_I": {"typeinfo": {"nation": "Common", "species": null, "type": "Modernization"}, "ships": ["PASB017_Montana_1945"]},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_dangling_references() {
+        let params = sample_params();
+        let resolver = ParamsResolver::new(&params);
+        let dangling = resolver.dangling();
+        let targets: Vec<(&str, ReferenceKind)> = dangling
+            .iter()
+            .map(|d| (d.reference.target.as_str(), d.reference.kind))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                ("PAPA999_Missing", ReferenceKind::Ammo),
+                ("B_Torpedoes", ReferenceKind::Component),
+                ("PASB018_Missing", ReferenceKind::Ship),
+            ]
+        );
+        assert_eq!(
+            dangling[0].reference.path,
+            "/A_Artillery/HP_AGM_1/ammoList/1"
+        );
+    }
+
+    #[test]
+    fn test_ship_view() {
+        let params = sample_params();
+        let resolver = ParamsResolver::new(&params);
+        let view = resolver.ship_view("PASB017_Montana_1945").unwrap();
+
+        assert_eq!(
+            view.params
+                .pointer("/A_Artillery/HP_AGM_1/ammoList/0/alphaDamage"),
+            Some(&json!(5700))
+        );
+        assert_eq!(
+            view.params.pointer("/A_Artillery/HP_AGM_1/ammoList/1"),
+            Some(&json!("PAPA999_Missing"))
+        );
+        assert_eq!(
+            view.params
+                .pointer("/ShipAbilities/AbilitySlot0/abils/0/0/numConsumables"),
+            Some(&json!(-1))
+        );
+        assert!(view.modernizations.contains_key("PCM001_MainGun_Mod_I"));
+        assert_eq!(view.dangling.len(), 3);
+
+        assert!(resolver.ship_view("PAPA001_406mm_HE").is_err());
+        assert!(resolver.ship_view("PZSB999").is_err());
+    }
+}
@@ -0,0 +1,313 @@
+// Split GameParams into one file per typeinfo type or one file per entity
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+
+use crate::types::UnpackResult;
+use crate::unpack::params_model::{Entity, GameParams};
+
+// entities without a typeinfo type
+const UNKNOWN_TYPE: &str = "Unknown";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitLayout {
+    /// dest/Ship.json with all ships keyed by name
+    PerType,
+    /// dest/Ship/PASB017_Montana_1945.json
+    PerEntity,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SplitSummary {
+    /// number of entities written for each type
+    pub types: BTreeMap<String, usize>,
+    pub files: usize,
+    pub skipped: usize,
+}
+
+pub struct ParamsSplitter {
+    layout: SplitLayout,
+    compact: bool,
+    include_types: Vec<String>,
+    exclude_types: Vec<String>,
+    include_nations: Vec<String>,
+    exclude_nations: Vec<String>,
+}
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// Keep file names portable, entity names are usually safe already
+fn sanitize(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // . and .. would point to dest or its parent
+    if sanitized.chars().all(|c| c == '.') {
+        return format!("_{}", sanitized);
+    }
+    sanitized
+}
+
+/**
+ * Sanitize a name and add a suffix when another name already took the same file name
+ * @param used The lowercase file names of the directory, case-insensitive file systems would merge them
+ * @param name The type or entity name
+ */
+fn unique_name(used: &mut HashSet<String>, name: &str) -> String {
+    let sanitized = sanitize(name);
+    let mut unique = sanitized.clone();
+    let mut suffix = 1;
+    while !used.insert(unique.to_lowercase()) {
+        suffix += 1;
+        unique = format!("{}_{}", sanitized, suffix);
+    }
+    if unique != sanitized {
+        warn!(
+            "{} is written as {} as {} is taken",
+            name, unique, sanitized
+        );
+    }
+    unique
+}
+
+impl ParamsSplitter {
+    pub fn new(layout: SplitLayout) -> Self {
+        Self {
+            layout,
+            compact: false,
+            include_types: Vec::new(),
+            exclude_types: Vec::new(),
+            include_nations: Vec::new(),
+            exclude_nations: Vec::new(),
+        }
+    }
+
+    /// Write json without pretty printing
+    pub fn compact(&mut self, compact: bool) -> &mut Self {
+        self.compact = compact;
+        self
+    }
+
+    /// Only keep these types, e.g. Ship and Modernization
+    pub fn include_types(&mut self, types: &[&str]) -> &mut Self {
+        self.include_types = to_strings(types);
+        self
+    }
+
+    pub fn exclude_types(&mut self, types: &[&str]) -> &mut Self {
+        self.exclude_types = to_strings(types);
+        self
+    }
+
+    /// Only keep these nations, e.g. USA and Japan
+    pub fn include_nations(&mut self, nations: &[&str]) -> &mut Self {
+        self.include_nations = to_strings(nations);
+        self
+    }
+
+    pub fn exclude_nations(&mut self, nations: &[&str]) -> &mut Self {
+        self.exclude_nations = to_strings(nations);
+        self
+    }
+
+    fn accepts(&self, kind: &str, nation: Option<&str>) -> bool {
+        let listed = |list: &Vec<String>, value: Option<&str>| match value {
+            Some(value) => list.iter().any(|item| item == value),
+            None => false,
+        };
+
+        if !self.include_types.is_empty() && !listed(&self.include_types, Some(kind)) {
+            return false;
+        }
+        if listed(&self.exclude_types, Some(kind)) {
+            return false;
+        }
+        if !self.include_nations.is_empty() && !listed(&self.include_nations, nation) {
+            return false;
+        }
+        !listed(&self.exclude_nations, nation)
+    }
+
+    fn write_json(&self, path: &Path, value: &impl Serialize) -> UnpackResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        if self.compact {
+            serde_json::to_writer(&mut writer, value)?;
+        } else {
+            serde_json::to_writer_pretty(&mut writer, value)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /**
+     * Write the selected entities into dest
+     * @param params The decoded GameParams
+     * @param dest The output directory
+     * @return The number of entities written for each type
+     */
+    pub fn split(&self, params: &GameParams, dest: &str) -> UnpackResult<SplitSummary> {
+        let mut summary = SplitSummary::default();
+        // sorted so the output is stable between runs
+        let mut grouped: BTreeMap<String, Vec<Entity>> = BTreeMap::new();
+        for entity in params.entities() {
+            let type_info = entity.type_info();
+            let kind = type_info
+                .kind
+                .as_deref()
+                .unwrap_or(UNKNOWN_TYPE)
+                .to_string();
+            if !self.accepts(&kind, type_info.nation.as_deref()) {
+                summary.skipped += 1;
+                continue;
+            }
+            grouped.entry(kind).or_default().push(entity);
+        }
+
+        std::fs::create_dir_all(dest)?;
+        let mut type_names = HashSet::new();
+        for (kind, entities) in grouped.iter_mut() {
+            let type_name = unique_name(&mut type_names, kind);
+            entities.sort_by(|a, b| a.name.cmp(b.name));
+            summary.types.insert(kind.clone(), entities.len());
+            match self.layout {
+                SplitLayout::PerType => {
+                    let mut map = Map::new();
+                    for entity in entities.iter() {
+                        map.insert(entity.name.to_string(), entity.data.clone());
+                    }
+                    let path = Path::new(dest).join(format!("{}.json", type_name));
+                    self.write_json(&path, &JsonValue::Object(map))?;
+                    summary.files += 1;
+                }
+                SplitLayout::PerEntity => {
+                    let type_dir: PathBuf = Path::new(dest).join(type_name);
+                    std::fs::create_dir_all(&type_dir)?;
+                    let mut entity_names = HashSet::new();
+                    for entity in entities.iter() {
+                        let entity_name = unique_name(&mut entity_names, entity.name);
+                        let path = type_dir.join(format!("{}.json", entity_name));
+                        self.write_json(&path, entity.data)?;
+                        summary.files += 1;
+                    }
+                }
+            }
+        }
+
+        info!(
+            "Split GameParams into {} files of {} types, skipped {} entities",
+            summary.files,
+            summary.types.len(),
+            summary.skipped
+        );
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sanitize, unique_name, ParamsSplitter, SplitLayout};
+    use crate::unpack::{params_model::GameParams, test_utils::temp_dir};
+    use serde_json::{json, Value as JsonValue};
+    use std::{collections::HashSet, path::Path};
+
+    fn sample_params() -> GameParams {
+        GameParams::new(json!({
+            "PASB017_Montana_1945": {"typeinfo": {"nation": "USA", "species": "Battleship", "type": "Ship"}},
+            "PJSB018_Yamato_1944": {"typeinfo": {"nation": "Japan", "species": "Battleship", "type": "Ship"}},
+            "PCM001_MainGun_Mod_I": {"typeinfo": {"nation": "Common", "species": null, "type": "Modernization"}},
+            "PAW001_Crew": {"typeinfo": {"nation": "USA", "species": null, "type": "Crew"}},
+        }))
+        .unwrap()
+    }
+
+    fn read_json(path: &Path) -> JsonValue {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_split_per_type() {
+        let dest = temp_dir("split_per_type");
+        let dest = dest.to_str().unwrap();
+        let summary = ParamsSplitter::new(SplitLayout::PerType)
+            .exclude_types(&["Crew"])
+            .split(&sample_params(), dest)
+            .unwrap();
+
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.types.get("Ship"), Some(&2));
+        let ships = read_json(&Path::new(dest).join("Ship.json"));
+        assert!(ships.get("PJSB018_Yamato_1944").is_some());
+        assert!(!Path::new(dest).join("Crew.json").exists());
+    }
+
+    #[test]
+    fn test_split_per_entity() {
+        let dest = temp_dir("split_per_entity");
+        let dest = dest.to_str().unwrap();
+        let summary = ParamsSplitter::new(SplitLayout::PerEntity)
+            .include_types(&["Ship", "Crew"])
+            .include_nations(&["USA"])
+            .compact(true)
+            .split(&sample_params(), dest)
+            .unwrap();
+
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.skipped, 2);
+        let montana = read_json(&Path::new(dest).join("Ship/PASB017_Montana_1945.json"));
+        assert_eq!(montana["typeinfo"]["nation"], "USA");
+        assert!(Path::new(dest).join("Crew/PAW001_Crew.json").exists());
+        assert!(!Path::new(dest)
+            .join("Ship/PJSB018_Yamato_1944.json")
+            .exists());
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("PASB017_Montana_1945"), "PASB017_Montana_1945");
+        assert_eq!(sanitize("a/b c"), "a_b_c");
+        assert_eq!(sanitize("."), "_.");
+        assert_eq!(sanitize(".."), "_..");
+        assert_eq!(sanitize(""), "_");
+        assert_eq!(sanitize("..a"), "..a");
+    }
+
+    #[test]
+    fn test_colliding_names() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_name(&mut used, "a/b"), "a_b");
+        assert_eq!(unique_name(&mut used, "A_B"), "A_B_2");
+        assert_eq!(unique_name(&mut used, "a_b_2"), "a_b_2_2");
+
+        let params = GameParams::new(json!({
+            "a/b": {"id": 1, "typeinfo": {"nation": "USA", "species": null, "type": "Ship"}},
+            "a b": {"id": 2, "typeinfo": {"nation": "USA", "species": null, "type": "Ship"}},
+        }))
+        .unwrap();
+        let dest = temp_dir("split_colliding");
+        let summary = ParamsSplitter::new(SplitLayout::PerEntity)
+            .split(&params, dest.to_str().unwrap())
+            .unwrap();
+        assert_eq!(summary.files, 2);
+        // sorted by name, so a b is written first
+        assert_eq!(read_json(&dest.join("Ship/a_b.json"))["id"], 2);
+        assert_eq!(read_json(&dest.join("Ship/a_b_2.json"))["id"], 1);
+    }
+}
@@ -0,0 +1,453 @@
+// Lossless model of the unpickled GameParams which keeps the Python types
+// Serialized as tagged json, e.g. {"type": "tuple", "value": [{"type": "int", "value": 1}]}
+
+use std::{collections::HashMap, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::UnpackResult;
+use crate::utils::pickle::{ObjectKind, Value};
+
+// cycles are kept as references, only deep nesting is rejected
+const MAX_DEPTH: usize = 512;
+
+/// An object of the pickle which has not been constructed, e.g. copy_reg._reconstructor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PyObject {
+    pub kind: ObjectKind,
+    pub callable: PyValue,
+    pub args: PyValue,
+    /// The keyword arguments of NEWOBJ_EX
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kwargs: Option<PyValue>,
+    /// None if the object has no BUILD, a None state is Some(PyValue::None)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<PyValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub list_items: Vec<PyValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dict_items: Vec<(PyValue, PyValue)>,
+}
+
+/**
+ * A Python value, a container referenced more than once is Shared where it first appears and Ref after
+ * Dicts keep their order and keys of any type as a list of pairs
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum PyValue {
+    None,
+    Bool(bool),
+    Int(i64),
+    /// Python 2 long, written as a string since json numbers are not that large
+    Long(#[serde(with = "long_repr")] i128),
+    /// NaN and infinity are written as strings
+    Float(#[serde(with = "float_repr")] f64),
+    /// Python 2 str, written as a latin1 string so every byte is kept
+    Bytes(#[serde(with = "bytes_repr")] Vec<u8>),
+    /// Python 2 unicode and Python 3 str
+    Str(String),
+    Tuple(Vec<PyValue>),
+    List(Vec<PyValue>),
+    Dict(Vec<(PyValue, PyValue)>),
+    Set(Vec<PyValue>),
+    FrozenSet(Vec<PyValue>),
+    Global {
+        module: String,
+        name: String,
+    },
+    Object(Box<PyObject>),
+    /// The first occurrence of a container which is referenced again, ids count from 0
+    Shared {
+        id: u64,
+        value: Box<PyValue>,
+    },
+    /// Another reference to the Shared value with this id
+    Ref(u64),
+}
+
+/// The address of a container, the same address is the same Python object
+fn address(value: &Value) -> Option<usize> {
+    let address = match value {
+        Value::Tuple(items) | Value::FrozenSet(items) => Rc::as_ptr(items) as *const (),
+        Value::List(items) | Value::Set(items) => Rc::as_ptr(items) as *const (),
+        Value::Dict(pairs) => Rc::as_ptr(pairs) as *const (),
+        Value::Object(object) => Rc::as_ptr(object) as *const (),
+        _ => return None,
+    };
+    Some(address as usize)
+}
+
+fn children(value: &Value) -> Vec<Value> {
+    let pairs = |pairs: &[(Value, Value)]| {
+        pairs
+            .iter()
+            .flat_map(|(key, value)| [key.clone(), value.clone()])
+            .collect()
+    };
+    match value {
+        Value::Tuple(items) | Value::FrozenSet(items) => items.to_vec(),
+        Value::List(items) | Value::Set(items) => items.borrow().clone(),
+        Value::Dict(items) => pairs(&items.borrow()),
+        Value::Object(object) => {
+            let object = object.borrow();
+            let mut children = vec![object.callable.clone(), object.args.clone()];
+            children.extend(object.kwargs.iter().cloned());
+            children.extend(object.state.iter().cloned());
+            children.extend(object.list_items.iter().cloned());
+            children.extend(pairs(&object.dict_items));
+            children
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Copies the values of the pickle machine and keeps which containers are the same object
+#[derive(Default)]
+struct Converter {
+    /// How often each container is referenced, by address
+    references: HashMap<usize, usize>,
+    ids: HashMap<usize, u64>,
+}
+
+impl Converter {
+    fn count(&mut self, value: &Value, depth: usize) -> UnpackResult<()> {
+        if depth > MAX_DEPTH {
+            return Err(Box::from("GameParams is nested too deeply"));
+        }
+
+        if let Some(address) = address(value) {
+            let references = self.references.entry(address).or_insert(0);
+            *references += 1;
+            // the children have been counted the first time
+            if *references > 1 {
+                return Ok(());
+            }
+        }
+        for child in children(value) {
+            self.count(&child, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn convert(&mut self, value: &Value, depth: usize) -> UnpackResult<PyValue> {
+        if depth > MAX_DEPTH {
+            return Err(Box::from("GameParams is nested too deeply"));
+        }
+
+        if let Some(address) = address(value) {
+            if let Some(id) = self.ids.get(&address) {
+                return Ok(PyValue::Ref(*id));
+            }
+            if self.references.get(&address).copied().unwrap_or(0) > 1 {
+                // assigned before the children, so a cycle becomes a Ref
+                let id = self.ids.len() as u64;
+                self.ids.insert(address, id);
+                let value = self.convert_value(value, depth)?;
+                return Ok(PyValue::Shared {
+                    id,
+                    value: Box::new(value),
+                });
+            }
+        }
+        self.convert_value(value, depth)
+    }
+
+    fn items(&mut self, items: &[Value], depth: usize) -> UnpackResult<Vec<PyValue>> {
+        items
+            .iter()
+            .map(|item| self.convert(item, depth + 1))
+            .collect()
+    }
+
+    fn pairs(
+        &mut self,
+        items: &[(Value, Value)],
+        depth: usize,
+    ) -> UnpackResult<Vec<(PyValue, PyValue)>> {
+        items
+            .iter()
+            .map(|(key, value)| {
+                Ok((
+                    self.convert(key, depth + 1)?,
+                    self.convert(value, depth + 1)?,
+                ))
+            })
+            .collect()
+    }
+
+    fn optional(&mut self, value: &Option<Value>, depth: usize) -> UnpackResult<Option<PyValue>> {
+        match value {
+            Some(value) => Ok(Some(self.convert(value, depth + 1)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn convert_value(&mut self, value: &Value, depth: usize) -> UnpackResult<PyValue> {
+        let value = match value {
+            Value::None => PyValue::None,
+            Value::Bool(value) => PyValue::Bool(*value),
+            Value::Int(value) => PyValue::Int(*value),
+            Value::Long(value) => PyValue::Long(*value),
+            Value::Float(value) => PyValue::Float(*value),
+            Value::Bytes(data) => PyValue::Bytes(data.to_vec()),
+            Value::String(text) => PyValue::Str(text.to_string()),
+            Value::Tuple(items) => PyValue::Tuple(self.items(items, depth)?),
+            Value::List(items) => PyValue::List(self.items(&items.borrow(), depth)?),
+            Value::Dict(items) => PyValue::Dict(self.pairs(&items.borrow(), depth)?),
+            Value::Set(items) => PyValue::Set(self.items(&items.borrow(), depth)?),
+            Value::FrozenSet(items) => PyValue::FrozenSet(self.items(items, depth)?),
+            Value::Global(global) => PyValue::Global {
+                module: global.0.clone(),
+                name: global.1.clone(),
+            },
+            Value::Object(object) => {
+                let object = object.borrow();
+                PyValue::Object(Box::new(PyObject {
+                    kind: object.kind,
+                    callable: self.convert(&object.callable, depth + 1)?,
+                    args: self.convert(&object.args, depth + 1)?,
+                    kwargs: self.optional(&object.kwargs, depth)?,
+                    state: self.optional(&object.state, depth)?,
+                    list_items: self.items(&object.list_items, depth)?,
+                    dict_items: self.pairs(&object.dict_items, depth)?,
+                }))
+            }
+        };
+        Ok(value)
+    }
+}
+
+impl PyValue {
+    /// Copy the shared values of the pickle machine into an owned tree
+    pub(crate) fn from_pickle(value: &Value, depth: usize) -> UnpackResult<Self> {
+        let mut converter = Converter::default();
+        converter.count(value, depth)?;
+        converter.convert(value, depth)
+    }
+
+    /// The value of a Shared container, other values are returned as they are
+    pub fn shared_value(&self) -> &PyValue {
+        match self {
+            PyValue::Shared { value, .. } => value.shared_value(),
+            value => value,
+        }
+    }
+
+    /// The params of the root tuple of GameParams.data
+    pub fn params(&self) -> Option<&PyValue> {
+        match self.shared_value() {
+            PyValue::Tuple(items) | PyValue::List(items) => items.first(),
+            _ => None,
+        }
+    }
+
+    /// Get a value of a dict or of the state of an object by its string key
+    pub fn get(&self, key: &str) -> Option<&PyValue> {
+        let pairs = match self.shared_value() {
+            PyValue::Dict(pairs) => pairs,
+            PyValue::Object(object) => match object.state.as_ref().map(PyValue::shared_value) {
+                Some(PyValue::Dict(pairs)) => pairs,
+                _ => &object.dict_items,
+            },
+            _ => return None,
+        };
+        pairs
+            .iter()
+            .find(|(name, _)| name.as_str() == Some(key))
+            .map(|(_, value)| value)
+    }
+
+    /// The text of a str or of a Python 2 str which is valid utf8
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PyValue::Str(text) => Some(text),
+            PyValue::Bytes(data) => std::str::from_utf8(data).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn to_tagged_json(&self, compact: bool) -> UnpackResult<String> {
+        if compact {
+            Ok(serde_json::to_string(self)?)
+        } else {
+            Ok(serde_json::to_string_pretty(self)?)
+        }
+    }
+
+    pub fn from_tagged_json(json: &str) -> UnpackResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+mod long_repr {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &i128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i128, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+mod float_repr {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FloatRepr {
+        Number(f64),
+        Text(String),
+    }
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_nan() {
+            serializer.serialize_str("nan")
+        } else if value.is_infinite() {
+            serializer.serialize_str(if *value > 0.0 { "inf" } else { "-inf" })
+        } else {
+            serializer.serialize_f64(*value)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        match FloatRepr::deserialize(deserializer)? {
+            FloatRepr::Number(value) => Ok(value),
+            FloatRepr::Text(text) => match text.as_str() {
+                "nan" => Ok(f64::NAN),
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                text => Err(D::Error::custom(format!("Invalid float {}", text))),
+            },
+        }
+    }
+}
+
+mod bytes_repr {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let text: String = data.iter().map(|c| *c as char).collect();
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        String::deserialize(deserializer)?
+            .chars()
+            .map(|c| u8::try_from(c).map_err(|_| D::Error::custom("Bytes are not latin1")))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PyObject, PyValue};
+    use crate::utils::pickle::{loads, ObjectKind};
+
+    #[test]
+    fn test_lossless_value() {
+        // (DataTable with {'name': 'PASB017', u'range': 18.5, 'ids': (1, 2L), 'mods': [None]},)
+        let data = b"(ccopy_reg\n_reconstructor\n(cGameParams\nGPData\nc__builtin__\nobject\nNtR}(U\x04nameU\x07PASB017Vrange\nG@2\x80\x00\x00\x00\x00\x00U\x03ids(K\x01L2L\ntU\x04mods]Naubt.";
+        let root = PyValue::from_pickle(&loads(data).unwrap(), 0).unwrap();
+        let params = root.params().unwrap();
+        match params {
+            PyValue::Object(object) => {
+                assert_eq!(object.kind, ObjectKind::Reduce);
+                assert_eq!(
+                    object.callable,
+                    PyValue::Global {
+                        module: "copy_reg".to_string(),
+                        name: "_reconstructor".to_string()
+                    }
+                );
+            }
+            value => panic!("Unexpected {:?}", value),
+        }
+
+        assert_eq!(
+            params.get("name"),
+            Some(&PyValue::Bytes(b"PASB017".to_vec()))
+        );
+        assert_eq!(params.get("range"), Some(&PyValue::Float(18.5)));
+        assert_eq!(
+            params.get("ids"),
+            Some(&PyValue::Tuple(vec![PyValue::Int(1), PyValue::Long(2)]))
+        );
+        assert_eq!(
+            params.get("mods"),
+            Some(&PyValue::List(vec![PyValue::None]))
+        );
+        assert!(params.get("missing").is_none());
+
+        let json = root.to_tagged_json(true).unwrap();
+        assert!(json.contains(r#"{"type":"long","value":"2"}"#));
+        assert!(json.contains(r#"{"type":"str","value":"range"}"#));
+        assert_eq!(PyValue::from_tagged_json(&json).unwrap(), root);
+    }
+
+    #[test]
+    fn test_tagged_json_special_values() {
+        let value = PyValue::List(vec![
+            PyValue::Float(f64::INFINITY),
+            PyValue::Bytes(vec![0, 0xe9, 0xff]),
+            PyValue::Long(i128::MIN),
+        ]);
+        let json = value.to_tagged_json(false).unwrap();
+        assert_eq!(PyValue::from_tagged_json(&json).unwrap(), value);
+
+        let nan = PyValue::from_tagged_json(r#"{"type": "float", "value": "nan"}"#).unwrap();
+        assert!(matches!(nan, PyValue::Float(value) if value.is_nan()));
+        assert!(PyValue::from_tagged_json(r#"{"type": "bytes", "value": "Ā"}"#).is_err());
+    }
+
+    #[test]
+    fn test_shared_values() {
+        // l = [1]; l.append(l); (l, {'a': l}, l) with protocol 2
+        let data = b"\x80\x02]q\x00(K\x01h\x00e}q\x01X\x01\x00\x00\x00aq\x02h\x00sh\x00\x87q\x03.";
+        let root = PyValue::from_pickle(&loads(data).unwrap(), 0).unwrap();
+        assert_eq!(
+            root,
+            PyValue::Tuple(vec![
+                PyValue::Shared {
+                    id: 0,
+                    value: Box::new(PyValue::List(vec![PyValue::Int(1), PyValue::Ref(0)])),
+                },
+                PyValue::Dict(vec![(PyValue::Str("a".to_string()), PyValue::Ref(0))]),
+                PyValue::Ref(0),
+            ])
+        );
+        assert!(matches!(
+            root.params().unwrap().shared_value(),
+            PyValue::List(_)
+        ));
+    }
+
+    #[test]
+    fn test_newobj_ex_kwargs() {
+        // GameParams.K.__new__(GameParams.K, 1, b=2) with protocol 4
+        let data = b"\x80\x04\x95#\x00\x00\x00\x00\x00\x00\x00\x8c\nGameParams\x94\x8c\x01K\x94\x93\x94K\x01\x85\x94}\x94\x8c\x01b\x94K\x02s\x92\x94.";
+        let root = PyValue::from_pickle(&loads(data).unwrap(), 0).unwrap();
+        assert_eq!(
+            root,
+            PyValue::Object(Box::new(PyObject {
+                kind: ObjectKind::NewObj,
+                callable: PyValue::Global {
+                    module: "GameParams".to_string(),
+                    name: "K".to_string(),
+                },
+                args: PyValue::Tuple(vec![PyValue::Int(1)]),
+                kwargs: Some(PyValue::Dict(vec![(
+                    PyValue::Str("b".to_string()),
+                    PyValue::Int(2)
+                )])),
+                state: None,
+                list_items: vec![],
+                dict_items: vec![],
+            }))
+        );
+    }
+}
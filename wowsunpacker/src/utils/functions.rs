@@ -1,6 +1,9 @@
 use crate::types::UnpackResult;
 use log::{error, warn};
-use std::{fs::OpenOptions, io::Write};
+use std::{
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+};
 
 pub fn read_string(data: &[u8], offset: usize) -> Option<String> {
     let mut length = 0;
@@ -38,7 +41,58 @@ pub fn write_file_data(file_name: &str, data: &[u8]) -> UnpackResult<()> {
     OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(file_name)?
         .write_all(data)?;
     Ok(())
 }
+
+/// Read a file from the end to the start in chunks, so reversed data does not need a reversed copy
+pub struct ReverseReader<R: Read + Seek> {
+    inner: R,
+    // bytes before the current chunk which have not been read yet
+    remaining: u64,
+    chunk: Vec<u8>,
+    position: usize,
+    chunk_size: usize,
+}
+
+impl<R: Read + Seek> ReverseReader<R> {
+    pub fn new(inner: R) -> std::io::Result<Self> {
+        Self::with_chunk_size(inner, 1 << 20)
+    }
+
+    pub fn with_chunk_size(mut inner: R, chunk_size: usize) -> std::io::Result<Self> {
+        let remaining = inner.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            inner,
+            remaining,
+            chunk: Vec::new(),
+            position: 0,
+            chunk_size: chunk_size.max(1),
+        })
+    }
+}
+
+impl<R: Read + Seek> Read for ReverseReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position == self.chunk.len() {
+            if self.remaining == 0 {
+                return Ok(0);
+            }
+
+            let size = self.remaining.min(self.chunk_size as u64);
+            self.remaining -= size;
+            self.inner.seek(SeekFrom::Start(self.remaining))?;
+            self.chunk.resize(size as usize, 0);
+            self.inner.read_exact(&mut self.chunk)?;
+            self.chunk.reverse();
+            self.position = 0;
+        }
+
+        let size = buf.len().min(self.chunk.len() - self.position);
+        buf[..size].copy_from_slice(&self.chunk[self.position..self.position + size]);
+        self.position += size;
+        Ok(size)
+    }
+}
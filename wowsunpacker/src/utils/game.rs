@@ -1,9 +1,11 @@
 extern crate winreg;
 use log::{info, warn};
-use std::{collections::HashMap, fmt, path::Path};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, path::Path, str::FromStr};
 use winreg::{enums::HKEY_CURRENT_USER, RegKey};
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
 pub enum GameServer {
     WW, // Global (ASIA, EU, NA, RU)
     CN, // The Chinese server
@@ -11,7 +13,7 @@ pub enum GameServer {
 }
 
 impl GameServer {
-    fn values() -> Vec<GameServer> {
+    pub fn values() -> Vec<GameServer> {
         vec![GameServer::WW, GameServer::CN, GameServer::PT]
     }
 
@@ -30,6 +32,27 @@ impl GameServer {
     }
 }
 
+impl FromStr for GameServer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ww" => Ok(GameServer::WW),
+            "cn" => Ok(GameServer::CN),
+            "pt" => Ok(GameServer::PT),
+            _ => Err(format!("Unknown game server {}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for GameServer {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
 pub struct GameDirectory {
     directory: HashMap<GameServer, String>,
 }
@@ -97,7 +120,8 @@ impl GameDirectory {
 ///
 
 #[allow(non_camel_case_types)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", try_from = "String")]
 pub enum GameLanguages {
     CS,
     DE,
@@ -126,6 +150,27 @@ impl fmt::Display for GameLanguages {
     }
 }
 
+/// Parse the folder name of a language, e.g. en or pt_br
+impl FromStr for GameLanguages {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let folder = s.trim().to_lowercase().replace('-', "_");
+        GameLanguages::values()
+            .into_iter()
+            .find(|lang| lang.to_folder_string() == folder)
+            .ok_or(format!("Unknown language {}", s))
+    }
+}
+
+impl TryFrom<String> for GameLanguages {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
 impl GameLanguages {
     pub fn to_folder_string(&self) -> String {
         self.to_string().to_lowercase()
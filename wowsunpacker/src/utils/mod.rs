@@ -1,11 +1,15 @@
 pub mod functions;
 pub mod game;
+pub mod pickle;
 
 #[cfg(test)]
 mod tests {
     use std::vec;
 
-    use super::{game::{GameDirectory, GameLanguages}, functions::read_string};
+    use super::{
+        functions::{read_string, ReverseReader},
+        game::{GameDirectory, GameLanguages},
+    };
     use crate::utils::game::GameServer;
 
     #[test]
@@ -22,6 +26,36 @@ mod tests {
         assert_eq!(langs.to_folder_string(), "ja");
     }
 
+    #[test]
+    fn test_parse_game_languages() {
+        for lang in GameLanguages::values() {
+            assert_eq!(lang.to_folder_string().parse::<GameLanguages>(), Ok(lang));
+        }
+        assert_eq!("PT-BR".parse::<GameLanguages>(), Ok(GameLanguages::PT_BR));
+        assert!("klingon".parse::<GameLanguages>().is_err());
+    }
+
+    #[test]
+    fn test_parse_game_server() {
+        assert_eq!("ww".parse::<GameServer>(), Ok(GameServer::WW));
+        assert_eq!("PT".parse::<GameServer>(), Ok(GameServer::PT));
+        assert!("ru".parse::<GameServer>().is_err());
+    }
+
+    #[test]
+    fn test_reverse_reader() {
+        use std::io::{Cursor, Read};
+
+        let data: Vec<u8> = (0..=255).collect();
+        let mut reversed = Vec::new();
+        ReverseReader::with_chunk_size(Cursor::new(&data), 7)
+            .unwrap()
+            .read_to_end(&mut reversed)
+            .unwrap();
+        let expected: Vec<u8> = data.iter().rev().copied().collect();
+        assert_eq!(reversed, expected);
+    }
+
     #[test]
     fn test_read_string() {
         let mut hello_world = vec![72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 00, 11, 22, 123, 44];
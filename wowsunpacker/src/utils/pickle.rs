@@ -0,0 +1,850 @@
+// A minimal pickle virtual machine, see Lib/pickletools.py in CPython for the opcodes
+// Objects are not constructed, classes are kept as globals with their arguments and state
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{BufRead, Read},
+    rc::Rc,
+};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::types::UnpackResult;
+
+/// How an object has been created in the pickle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectKind {
+    /// callable(*args), including copy_reg._reconstructor
+    Reduce,
+    /// cls.__new__(cls, *args, **kwargs), kwargs are only set by NEWOBJ_EX
+    NewObj,
+    /// old style class instance created by INST or OBJ
+    Inst,
+}
+
+#[derive(Debug)]
+pub struct Object {
+    pub kind: ObjectKind,
+    pub callable: Value,
+    pub args: Value,
+    /// the keyword arguments of NEWOBJ_EX
+    pub kwargs: Option<Value>,
+    /// the state set by BUILD
+    pub state: Option<Value>,
+    /// items added by APPEND(S), e.g. for list subclasses
+    pub list_items: Vec<Value>,
+    /// items added by SETITEM(S), e.g. for OrderedDict
+    pub dict_items: Vec<(Value, Value)>,
+}
+
+/// Containers are shared so objects from the memo see later changes
+#[derive(Debug, Clone)]
+pub enum Value {
+    None,
+    Bool(bool),
+    Int(i64),
+    /// Python 2 long, it is a different type even if it is small
+    Long(i128),
+    Float(f64),
+    /// Python 2 str and Python 3 bytes
+    Bytes(Rc<Vec<u8>>),
+    String(Rc<String>),
+    Tuple(Rc<Vec<Value>>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Dict(Rc<RefCell<Vec<(Value, Value)>>>),
+    Set(Rc<RefCell<Vec<Value>>>),
+    FrozenSet(Rc<Vec<Value>>),
+    Global(Rc<(String, String)>),
+    Object(Rc<RefCell<Object>>),
+}
+
+impl Value {
+    fn tuple(items: Vec<Value>) -> Self {
+        Value::Tuple(Rc::new(items))
+    }
+
+    pub fn as_global(&self) -> Option<(&str, &str)> {
+        match self {
+            Value::Global(global) => Some((global.0.as_str(), global.1.as_str())),
+            _ => None,
+        }
+    }
+}
+
+enum StackItem {
+    Mark,
+    Value(Value),
+}
+
+type DictItems = Rc<RefCell<Vec<(Value, Value)>>>;
+
+/// Called with every key and value which is set on the streamed dict
+pub type EntityCallback<'a> = Box<dyn FnMut(Value, Value) -> UnpackResult<()> + 'a>;
+
+pub struct Unpickler<'a, R: BufRead> {
+    reader: R,
+    stack: Vec<StackItem>,
+    memo: HashMap<u64, Value>,
+    /// the index of the next MEMOIZE, the memo shrinks when containers are released
+    next_memo: u64,
+    entity_callback: Option<EntityCallback<'a>>,
+    /// the first dict at the top level, e.g. the params of GameParams
+    streamed: Option<DictItems>,
+    /// memo of containers which are released after their entities have been streamed
+    released: Vec<u64>,
+}
+
+impl<'a, R: BufRead> Unpickler<'a, R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            stack: Vec::new(),
+            memo: HashMap::new(),
+            next_memo: 0,
+            entity_callback: None,
+            streamed: None,
+            released: Vec::new(),
+        }
+    }
+
+    /**
+     * Stream the items of the first dict at the top level instead of keeping them
+     * Containers in the memo are released after every SETITEMS to bound the memory,
+     * a pickle which shares them between entities fails with a missing memo.
+     * A protocol 0 dict (DICT) is still built in memory because its items come before the opcode
+     * @param callback Called with the key and the value of every item
+     */
+    pub fn with_entity_callback(
+        mut self,
+        callback: impl FnMut(Value, Value) -> UnpackResult<()> + 'a,
+    ) -> Self {
+        self.entity_callback = Some(Box::new(callback));
+        self
+    }
+
+    fn read_u8(&mut self) -> UnpackResult<u8> {
+        let mut buf = [0; 1];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_bytes(&mut self, size: u64) -> UnpackResult<Vec<u8>> {
+        let mut buf = Vec::new();
+        (&mut self.reader).take(size).read_to_end(&mut buf)?;
+        if buf.len() as u64 != size {
+            return Err(Box::from("Unexpected end of pickle data"));
+        }
+        Ok(buf)
+    }
+
+    fn read_u32(&mut self) -> UnpackResult<u32> {
+        let mut buf = [0; 4];
+        self.reader.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_u64(&mut self) -> UnpackResult<u64> {
+        let mut buf = [0; 8];
+        self.reader.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Read a line of a text opcode without the newline
+    fn read_line_bytes(&mut self) -> UnpackResult<Vec<u8>> {
+        let mut line = Vec::new();
+        self.reader.read_until(b'\n', &mut line)?;
+        if line.pop() != Some(b'\n') {
+            return Err(Box::from("Unexpected end of pickle data"));
+        }
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(line)
+    }
+
+    fn read_line(&mut self) -> UnpackResult<String> {
+        let line = self.read_line_bytes()?;
+        Ok(String::from_utf8(line)?)
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(StackItem::Value(value));
+    }
+
+    fn pop(&mut self) -> UnpackResult<Value> {
+        match self.stack.pop() {
+            Some(StackItem::Value(value)) => Ok(value),
+            Some(StackItem::Mark) => Err(Box::from("Unexpected mark on the pickle stack")),
+            None => Err(Box::from("Pickle stack underflow")),
+        }
+    }
+
+    fn top(&mut self) -> UnpackResult<&mut Value> {
+        match self.stack.last_mut() {
+            Some(StackItem::Value(value)) => Ok(value),
+            Some(StackItem::Mark) => Err(Box::from("Unexpected mark on the pickle stack")),
+            None => Err(Box::from("Pickle stack underflow")),
+        }
+    }
+
+    /// Pop everything until the last mark
+    fn pop_mark(&mut self) -> UnpackResult<Vec<Value>> {
+        let mut items = Vec::new();
+        loop {
+            match self.stack.pop() {
+                Some(StackItem::Value(value)) => items.push(value),
+                Some(StackItem::Mark) => break,
+                None => return Err(Box::from("Failed to find the mark on the pickle stack")),
+            }
+        }
+        items.reverse();
+        Ok(items)
+    }
+
+    fn memo_get(&self, index: u64) -> UnpackResult<Value> {
+        self.memo.get(&index).cloned().ok_or_else(|| {
+            if self.streamed.is_some() {
+                Box::from(format!(
+                    "Memo {} does not exist or has been released while streaming",
+                    index
+                ))
+            } else {
+                Box::from(format!("Memo {} does not exist", index))
+            }
+        })
+    }
+
+    fn memo_put(&mut self, index: u64) -> UnpackResult<()> {
+        let value = self.top()?.clone();
+        if self.streamed.is_some() && is_container(&value) {
+            self.released.push(index);
+        }
+        self.memo.insert(index, value);
+        self.next_memo = self.next_memo.max(index + 1);
+        Ok(())
+    }
+
+    fn push_dict(&mut self) {
+        let dict = Rc::new(RefCell::new(Vec::new()));
+        let top_level = self
+            .stack
+            .iter()
+            .all(|item| matches!(item, StackItem::Mark));
+        if self.entity_callback.is_some() && self.streamed.is_none() && top_level {
+            self.streamed = Some(dict.clone());
+        }
+        self.push(Value::Dict(dict));
+    }
+
+    fn is_streamed(&self) -> bool {
+        match (self.stack.last(), &self.streamed) {
+            (Some(StackItem::Value(Value::Dict(dict))), Some(streamed)) => {
+                Rc::ptr_eq(dict, streamed)
+            }
+            _ => false,
+        }
+    }
+
+    fn set_items(&mut self, items: Vec<Value>) -> UnpackResult<()> {
+        if !items.len().is_multiple_of(2) {
+            return Err(Box::from("Odd number of items for SETITEMS"));
+        }
+
+        let mut pairs = Vec::with_capacity(items.len() / 2);
+        let mut items = items.into_iter();
+        while let (Some(key), Some(value)) = (items.next(), items.next()) {
+            pairs.push((key, value));
+        }
+
+        if self.is_streamed() {
+            if let Some(callback) = self.entity_callback.as_mut() {
+                for (key, value) in pairs {
+                    callback(key, value)?;
+                }
+                for index in self.released.drain(..) {
+                    self.memo.remove(&index);
+                }
+                return Ok(());
+            }
+        }
+
+        match self.top()? {
+            Value::Dict(dict) => dict.borrow_mut().extend(pairs),
+            Value::Object(object) => object.borrow_mut().dict_items.extend(pairs),
+            _ => return Err(Box::from("SETITEMS on a value which is not a dict")),
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, items: Vec<Value>) -> UnpackResult<()> {
+        match self.top()? {
+            Value::List(list) => list.borrow_mut().extend(items),
+            Value::Object(object) => object.borrow_mut().list_items.extend(items),
+            _ => return Err(Box::from("APPENDS on a value which is not a list")),
+        }
+        Ok(())
+    }
+
+    fn new_object(&mut self, kind: ObjectKind, callable: Value, args: Value) {
+        let args = match args {
+            Value::Tuple(_) => args,
+            args => Value::tuple(vec![args]),
+        };
+        let object = Object {
+            kind,
+            callable,
+            args,
+            kwargs: None,
+            state: None,
+            list_items: Vec::new(),
+            dict_items: Vec::new(),
+        };
+        self.push(Value::Object(Rc::new(RefCell::new(object))));
+    }
+
+    fn reduce(&mut self, callable: Value, args: Value) -> UnpackResult<()> {
+        let items = match &args {
+            Value::Tuple(items) => items.clone(),
+            _ => return Err(Box::from("REDUCE arguments are not a tuple")),
+        };
+
+        // builtin containers are restored as containers
+        match callable.as_global() {
+            Some(("__builtin__" | "builtins", "set")) => {
+                let set = match items.first() {
+                    Some(value) => iterate(value)?,
+                    None => Vec::new(),
+                };
+                self.push(Value::Set(Rc::new(RefCell::new(set))));
+            }
+            Some(("__builtin__" | "builtins", "frozenset")) => {
+                let set = match items.first() {
+                    Some(value) => iterate(value)?,
+                    None => Vec::new(),
+                };
+                self.push(Value::FrozenSet(Rc::new(set)));
+            }
+            _ => self.new_object(ObjectKind::Reduce, callable, args),
+        }
+        Ok(())
+    }
+
+    fn build(&mut self) -> UnpackResult<()> {
+        let state = self.pop()?;
+        match self.top()? {
+            Value::Object(object) => {
+                let mut object = object.borrow_mut();
+                object.state = match object.state.take() {
+                    // __setstate__ of a DataTable updates the existing state
+                    Some(Value::Dict(existing)) => {
+                        if let Value::Dict(update) = &state {
+                            existing
+                                .borrow_mut()
+                                .extend(update.borrow().iter().cloned());
+                            Some(Value::Dict(existing))
+                        } else {
+                            Some(state)
+                        }
+                    }
+                    _ => Some(state),
+                }
+            }
+            Value::Dict(dict) => {
+                if let Value::Dict(update) = &state {
+                    let update: Vec<(Value, Value)> = update.borrow().clone();
+                    dict.borrow_mut().extend(update);
+                } else {
+                    return Err(Box::from("BUILD on a dict without a dict state"));
+                }
+            }
+            _ => return Err(Box::from("BUILD on a value which is not an object")),
+        }
+        Ok(())
+    }
+
+    fn read_long(&mut self, size: u64) -> UnpackResult<Value> {
+        let data = self.read_bytes(size)?;
+        Ok(Value::Long(decode_long(&data)?))
+    }
+
+    /// Run the pickle until STOP and return the unpickled value
+    pub fn load(&mut self) -> UnpackResult<Value> {
+        loop {
+            let opcode = self.read_u8()?;
+            match opcode {
+                // protocol 0 and 1
+                b'(' => self.stack.push(StackItem::Mark),
+                b'.' => break,
+                b'0' => {
+                    self.stack.pop();
+                }
+                b'1' => {
+                    self.pop_mark()?;
+                }
+                b'2' => {
+                    let value = self.top()?.clone();
+                    self.push(value);
+                }
+                b'F' => {
+                    let line = self.read_line()?;
+                    self.push(Value::Float(line.trim().parse()?));
+                }
+                b'I' => {
+                    let line = self.read_line()?;
+                    let value = match line.as_str() {
+                        "00" => Value::Bool(false),
+                        "01" => Value::Bool(true),
+                        line => Value::Int(line.trim().parse()?),
+                    };
+                    self.push(value);
+                }
+                b'J' => {
+                    let value = self.read_u32()? as i32;
+                    self.push(Value::Int(value as i64));
+                }
+                b'K' => {
+                    let value = self.read_u8()?;
+                    self.push(Value::Int(value as i64));
+                }
+                b'M' => {
+                    let data = self.read_bytes(2)?;
+                    self.push(Value::Int(u16::from_le_bytes([data[0], data[1]]) as i64));
+                }
+                b'L' => {
+                    let line = self.read_line()?;
+                    self.push(Value::Long(line.trim_end_matches('L').parse()?));
+                }
+                b'N' => self.push(Value::None),
+                b'R' => {
+                    let args = self.pop()?;
+                    let callable = self.pop()?;
+                    self.reduce(callable, args)?;
+                }
+                b'S' => {
+                    let line = self.read_line()?;
+                    self.push(Value::Bytes(Rc::new(decode_string_literal(&line)?)));
+                }
+                b'T' => {
+                    let size = self.read_u32()? as i32;
+                    if size < 0 {
+                        return Err(Box::from("Negative BINSTRING size"));
+                    }
+                    let data = self.read_bytes(size as u64)?;
+                    self.push(Value::Bytes(Rc::new(data)));
+                }
+                b'U' => {
+                    let size = self.read_u8()?;
+                    let data = self.read_bytes(size as u64)?;
+                    self.push(Value::Bytes(Rc::new(data)));
+                }
+                b'V' => {
+                    // raw-unicode-escape keeps characters below 256 as latin1
+                    let line: String = self.read_line_bytes()?.iter().map(|c| *c as char).collect();
+                    self.push(Value::String(Rc::new(decode_raw_unicode_escape(&line)?)));
+                }
+                b'X' => {
+                    let size = self.read_u32()?;
+                    let data = self.read_bytes(size as u64)?;
+                    self.push(Value::String(Rc::new(String::from_utf8(data)?)));
+                }
+                b'a' => {
+                    let value = self.pop()?;
+                    self.append(vec![value])?;
+                }
+                b'b' => self.build()?,
+                b'c' => {
+                    let module = self.read_line()?;
+                    let name = self.read_line()?;
+                    self.push(Value::Global(Rc::new((module, name))));
+                }
+                b'd' => {
+                    let items = self.pop_mark()?;
+                    self.push_dict();
+                    self.set_items(items)?;
+                }
+                b'}' => self.push_dict(),
+                b'e' => {
+                    let items = self.pop_mark()?;
+                    self.append(items)?;
+                }
+                b'g' => {
+                    let index = self.read_line()?.trim().parse()?;
+                    let value = self.memo_get(index)?;
+                    self.push(value);
+                }
+                b'h' => {
+                    let index = self.read_u8()? as u64;
+                    let value = self.memo_get(index)?;
+                    self.push(value);
+                }
+                b'j' => {
+                    let index = self.read_u32()? as u64;
+                    let value = self.memo_get(index)?;
+                    self.push(value);
+                }
+                b'i' => {
+                    let module = self.read_line()?;
+                    let name = self.read_line()?;
+                    let args = self.pop_mark()?;
+                    let callable = Value::Global(Rc::new((module, name)));
+                    self.new_object(ObjectKind::Inst, callable, Value::tuple(args));
+                }
+                b'o' => {
+                    let mut args = self.pop_mark()?;
+                    if args.is_empty() {
+                        return Err(Box::from("OBJ without a class"));
+                    }
+                    let callable = args.remove(0);
+                    self.new_object(ObjectKind::Inst, callable, Value::tuple(args));
+                }
+                b'l' => {
+                    let items = self.pop_mark()?;
+                    self.push(Value::List(Rc::new(RefCell::new(items))));
+                }
+                b']' => self.push(Value::List(Rc::new(RefCell::new(Vec::new())))),
+                b'p' => {
+                    let index = self.read_line()?.trim().parse()?;
+                    self.memo_put(index)?;
+                }
+                b'q' => {
+                    let index = self.read_u8()? as u64;
+                    self.memo_put(index)?;
+                }
+                b'r' => {
+                    let index = self.read_u32()? as u64;
+                    self.memo_put(index)?;
+                }
+                b's' => {
+                    let value = self.pop()?;
+                    let key = self.pop()?;
+                    self.set_items(vec![key, value])?;
+                }
+                b't' => {
+                    let items = self.pop_mark()?;
+                    self.push(Value::tuple(items));
+                }
+                b')' => self.push(Value::tuple(Vec::new())),
+                b'u' => {
+                    let items = self.pop_mark()?;
+                    self.set_items(items)?;
+                }
+                b'G' => {
+                    let data = self.read_bytes(8)?;
+                    let mut buf = [0; 8];
+                    buf.copy_from_slice(&data);
+                    self.push(Value::Float(f64::from_be_bytes(buf)));
+                }
+                // protocol 2
+                0x80 => {
+                    let protocol = self.read_u8()?;
+                    debug!("Pickle protocol {}", protocol);
+                    if protocol > 5 {
+                        return Err(Box::from(format!(
+                            "Unsupported pickle protocol {}",
+                            protocol
+                        )));
+                    }
+                }
+                0x81 => {
+                    let args = self.pop()?;
+                    let cls = self.pop()?;
+                    self.new_object(ObjectKind::NewObj, cls, args);
+                }
+                0x85 => {
+                    let first = self.pop()?;
+                    self.push(Value::tuple(vec![first]));
+                }
+                0x86 => {
+                    let second = self.pop()?;
+                    let first = self.pop()?;
+                    self.push(Value::tuple(vec![first, second]));
+                }
+                0x87 => {
+                    let third = self.pop()?;
+                    let second = self.pop()?;
+                    let first = self.pop()?;
+                    self.push(Value::tuple(vec![first, second, third]));
+                }
+                0x88 => self.push(Value::Bool(true)),
+                0x89 => self.push(Value::Bool(false)),
+                0x8a => {
+                    let size = self.read_u8()? as u64;
+                    let value = self.read_long(size)?;
+                    self.push(value);
+                }
+                0x8b => {
+                    let size = self.read_u32()? as i32;
+                    if size < 0 {
+                        return Err(Box::from("Negative LONG4 size"));
+                    }
+                    let value = self.read_long(size as u64)?;
+                    self.push(value);
+                }
+                // protocol 3
+                b'B' => {
+                    let size = self.read_u32()?;
+                    let data = self.read_bytes(size as u64)?;
+                    self.push(Value::Bytes(Rc::new(data)));
+                }
+                b'C' => {
+                    let size = self.read_u8()?;
+                    let data = self.read_bytes(size as u64)?;
+                    self.push(Value::Bytes(Rc::new(data)));
+                }
+                // protocol 4
+                0x8c => {
+                    let size = self.read_u8()?;
+                    let data = self.read_bytes(size as u64)?;
+                    self.push(Value::String(Rc::new(String::from_utf8(data)?)));
+                }
+                0x8d => {
+                    let size = self.read_u64()?;
+                    let data = self.read_bytes(size)?;
+                    self.push(Value::String(Rc::new(String::from_utf8(data)?)));
+                }
+                0x8e | 0x96 => {
+                    let size = self.read_u64()?;
+                    let data = self.read_bytes(size)?;
+                    self.push(Value::Bytes(Rc::new(data)));
+                }
+                0x8f => self.push(Value::Set(Rc::new(RefCell::new(Vec::new())))),
+                0x90 => {
+                    let items = self.pop_mark()?;
+                    match self.top()? {
+                        Value::Set(set) => set.borrow_mut().extend(items),
+                        _ => return Err(Box::from("ADDITEMS on a value which is not a set")),
+                    }
+                }
+                0x91 => {
+                    let items = self.pop_mark()?;
+                    self.push(Value::FrozenSet(Rc::new(items)));
+                }
+                0x92 => {
+                    let kwargs = self.pop()?;
+                    let args = self.pop()?;
+                    let cls = self.pop()?;
+                    self.new_object(ObjectKind::NewObj, cls, args);
+                    if let Value::Object(object) = self.top()? {
+                        object.borrow_mut().kwargs = Some(kwargs);
+                    }
+                }
+                0x93 => {
+                    let name = self.pop()?;
+                    let module = self.pop()?;
+                    match (module, name) {
+                        (Value::String(module), Value::String(name)) => {
+                            let global = (module.to_string(), name.to_string());
+                            self.push(Value::Global(Rc::new(global)));
+                        }
+                        _ => return Err(Box::from("STACK_GLOBAL requires two strings")),
+                    }
+                }
+                0x94 => {
+                    self.memo_put(self.next_memo)?;
+                }
+                0x95 => {
+                    // frames are only a hint for buffering
+                    self.read_u64()?;
+                }
+                _ => {
+                    return Err(Box::from(format!(
+                        "Unsupported pickle opcode 0x{:02x}",
+                        opcode
+                    )))
+                }
+            }
+        }
+
+        let value = self.pop()?;
+        self.stack.clear();
+        self.memo.clear();
+        self.next_memo = 0;
+        Ok(value)
+    }
+}
+
+fn is_container(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Tuple(_)
+            | Value::List(_)
+            | Value::Dict(_)
+            | Value::Set(_)
+            | Value::FrozenSet(_)
+            | Value::Object(_)
+    )
+}
+
+/// Get the items of a list, tuple or set argument
+fn iterate(value: &Value) -> UnpackResult<Vec<Value>> {
+    match value {
+        Value::List(list) => Ok(list.borrow().clone()),
+        Value::Tuple(items) => Ok(items.to_vec()),
+        Value::Set(set) => Ok(set.borrow().clone()),
+        Value::FrozenSet(set) => Ok(set.to_vec()),
+        _ => Err(Box::from("Value is not iterable")),
+    }
+}
+
+/// Decode a little endian two's complement integer of LONG1 and LONG4
+fn decode_long(data: &[u8]) -> UnpackResult<i128> {
+    if data.is_empty() {
+        return Ok(0);
+    }
+    if data.len() > 16 {
+        return Err(Box::from(format!(
+            "Long with {} bytes is too large",
+            data.len()
+        )));
+    }
+
+    let fill = if data[data.len() - 1] & 0x80 != 0 {
+        0xff
+    } else {
+        0
+    };
+    let mut buf = [fill; 16];
+    buf[..data.len()].copy_from_slice(data);
+    Ok(i128::from_le_bytes(buf))
+}
+
+/// Decode the repr() of a Python 2 str, e.g. 'it\'s'
+fn decode_string_literal(line: &str) -> UnpackResult<Vec<u8>> {
+    let bytes = line.as_bytes();
+    if bytes.len() < 2 || bytes[0] != bytes[bytes.len() - 1] || !matches!(bytes[0], b'\'' | b'"') {
+        return Err(Box::from(format!("Invalid string literal {}", line)));
+    }
+
+    let body = &bytes[1..bytes.len() - 1];
+    let mut output = Vec::with_capacity(body.len());
+    let mut i = 0;
+    while i < body.len() {
+        if body[i] != b'\\' || i + 1 >= body.len() {
+            output.push(body[i]);
+            i += 1;
+            continue;
+        }
+
+        i += 1;
+        match body[i] {
+            b'n' => output.push(b'\n'),
+            b'r' => output.push(b'\r'),
+            b't' => output.push(b'\t'),
+            b'0'..=b'7' => {
+                let end = (i + 3).min(body.len());
+                let digits: String = body[i..end]
+                    .iter()
+                    .take_while(|c| (b'0'..=b'7').contains(c))
+                    .map(|c| *c as char)
+                    .collect();
+                output.push(u8::from_str_radix(&digits, 8)?);
+                i += digits.len() - 1;
+            }
+            b'x' if i + 2 < body.len() => {
+                let hex = std::str::from_utf8(&body[i + 1..i + 3])?;
+                output.push(u8::from_str_radix(hex, 16)?);
+                i += 2;
+            }
+            other => output.push(other),
+        }
+        i += 1;
+    }
+    Ok(output)
+}
+
+/// Decode the raw-unicode-escape encoding of UNICODE, only \uXXXX and \UXXXXXXXX are escaped
+fn decode_raw_unicode_escape(line: &str) -> UnpackResult<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut output = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let size = match (chars[i], chars.get(i + 1)) {
+            ('\\', Some('u')) => 4,
+            ('\\', Some('U')) => 8,
+            _ => 0,
+        };
+        if size == 0 || i + 2 + size > chars.len() {
+            output.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        let hex: String = chars[i + 2..i + 2 + size].iter().collect();
+        let code = u32::from_str_radix(&hex, 16)?;
+        output.push(char::from_u32(code).ok_or("Invalid unicode escape")?);
+        i += 2 + size;
+    }
+    Ok(output)
+}
+
+/// Unpickle data which is completely in memory
+pub fn loads(data: &[u8]) -> UnpackResult<Value> {
+    Unpickler::new(data).load()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{loads, Unpickler, Value};
+
+    #[test]
+    fn test_protocol_2() {
+        // ({'a': [1, 2]}, <same dict>, 2**70, -5, True, (1, 2), {3})
+        let data = b"\x80\x02(}q\x00X\x01\x00\x00\x00aq\x01]q\x02(K\x01K\x02esh\x00\x8a\t\x00\x00\x00\x00\x00\x00\x00\x00@J\xfb\xff\xff\xff\x88K\x01K\x02\x86q\x03c__builtin__\nset\nq\x04]q\x05K\x03a\x85q\x06Rq\x07tq\x08.";
+        let items = match loads(data).unwrap() {
+            Value::Tuple(items) => items,
+            value => panic!("Unexpected {:?}", value),
+        };
+        assert_eq!(items.len(), 7);
+
+        // the memo shares the dict which has been filled after it was memoized
+        match (&items[0], &items[1]) {
+            (Value::Dict(first), Value::Dict(second)) => {
+                assert!(std::rc::Rc::ptr_eq(first, second));
+                assert_eq!(second.borrow().len(), 1);
+            }
+            value => panic!("Unexpected {:?}", value),
+        }
+        assert!(matches!(items[2], Value::Long(value) if value == 1 << 70));
+        assert!(matches!(items[3], Value::Int(-5)));
+        assert!(matches!(items[4], Value::Bool(true)));
+        assert!(matches!(&items[5], Value::Tuple(pair) if pair.len() == 2));
+        assert!(matches!(&items[6], Value::Set(set) if set.borrow().len() == 1));
+    }
+
+    #[test]
+    fn test_protocol_0() {
+        // ["it's", u"\xe9\\u", 1.5, 12345678901234L]
+        let data = b"(lp0\nVit's\np1\naV\xe9\\u005cu\np2\naF1.5\naL12345678901234L\na.";
+        let items = match loads(data).unwrap() {
+            Value::List(items) => items.borrow().clone(),
+            value => panic!("Unexpected {:?}", value),
+        };
+        assert!(matches!(&items[0], Value::String(text) if text.as_str() == "it's"));
+        assert!(matches!(&items[1], Value::String(text) if text.as_str() == "\u{e9}\\u"));
+        assert!(matches!(items[2], Value::Float(value) if value == 1.5));
+        assert!(matches!(items[3], Value::Long(12345678901234)));
+
+        assert!(loads(b"(lp0\n").is_err());
+        assert!(loads(b"\xff.").is_err());
+    }
+
+    #[test]
+    fn test_memoize_after_release() {
+        // {'a': [], 'b': 'a'} with protocol 4 in two SETITEMS, the list is released after the first
+        let data = b"\x80\x04}\x94(\x8c\x01a\x94]\x94u(\x8c\x01b\x94h\x01u.";
+        let mut items = Vec::new();
+        Unpickler::new(&data[..])
+            .with_entity_callback(|key, value| {
+                items.push((key, value));
+                Ok(())
+            })
+            .load()
+            .unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(matches!(&items[1].0, Value::String(text) if text.as_str() == "b"));
+        assert!(matches!(&items[1].1, Value::String(text) if text.as_str() == "a"));
+    }
+}